@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+
+use opengl_graphics::{Texture, TextureSettings};
+
+use crate::GameError;
+
+// * Where bundled assets live, SNAKE_ASSETS overrides the assets folder next to the game
+fn assets_root() -> Option<PathBuf> {
+    if let Some(root) = std::env::var_os("SNAKE_ASSETS") {
+        return Some(PathBuf::from(root));
+    }
+
+    let exe = std::env::current_exe().ok()?;
+    exe.parent()
+        .map(|dir| dir.join("assets"))
+        .filter(|dir| dir.is_dir())
+        .or_else(|| Some(PathBuf::from("assets")))
+}
+
+// * Paths that exist as given win, anything else is looked up in the assets root
+pub fn resolve(path: &str) -> Result<PathBuf, GameError> {
+    let given = Path::new(path);
+    if given.is_file() {
+        return Ok(given.to_path_buf());
+    }
+
+    assets_root()
+        .map(|root| root.join(given))
+        .filter(|path| path.is_file())
+        .ok_or_else(|| GameError::Asset(format!("{} not found", path)))
+}
+
+pub fn load_texture(path: &str) -> Result<Texture, GameError> {
+    let resolved = resolve(path)?;
+    Texture::from_path(&resolved, &TextureSettings::new())
+        .map_err(|err| GameError::Asset(format!("could not load {}: {}", path, err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // * A file that exists but isn't an image, so nothing here needs a GL context
+    fn scratch_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}", std::process::id(), name));
+        std::fs::write(&path, b"not an image").unwrap();
+        path
+    }
+
+    #[test]
+    fn existing_paths_resolve_as_given() {
+        let path = scratch_file("resolve.png");
+
+        let resolved = resolve(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(resolved, path);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn missing_textures_are_reported_before_loading() {
+        let Err(GameError::Asset(message)) = load_texture("no-such-texture.png") else {
+            panic!("expected an asset error");
+        };
+        assert_eq!(message, "no-such-texture.png not found");
+    }
+
+    #[test]
+    fn found_files_that_fail_to_load_are_asset_errors() {
+        let path = scratch_file("texture.png");
+
+        let Err(GameError::Asset(message)) = load_texture(path.to_str().unwrap()) else {
+            panic!("expected an asset error");
+        };
+
+        assert!(message.starts_with("could not load"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+use graphics::math::Matrix2d;
+use graphics::Graphics;
+
+// * Tiny built-in 3x5 bitmap font, so text needs no font files.
+// * Each row is 3 bits wide with the leftmost pixel in the highest bit.
+pub const GLYPH_WIDTH: usize = 3;
+pub const GLYPH_HEIGHT: usize = 5;
+
+pub fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        // * Anything else shows up as a question mark
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+// * Lit pixels of a line of text as (column, row), glyphs are one pixel apart
+pub fn text_pixels(text: &str) -> Vec<(usize, usize)> {
+    text.chars()
+        .enumerate()
+        .flat_map(|(i, c)| {
+            let rows = glyph(c);
+            (0..GLYPH_HEIGHT).flat_map(move |row| {
+                (0..GLYPH_WIDTH)
+                    .filter(move |column| rows[row] & (0b100 >> column) != 0)
+                    .map(move |column| (i * (GLYPH_WIDTH + 1) + column, row))
+            })
+        })
+        .collect()
+}
+
+pub fn text_width(text: &str, pixel: f64) -> f64 {
+    let columns = (text.chars().count() * (GLYPH_WIDTH + 1)).saturating_sub(1);
+    columns as f64 * pixel
+}
+
+pub fn text_height(pixel: f64) -> f64 {
+    GLYPH_HEIGHT as f64 * pixel
+}
+
+pub fn draw_text<G: Graphics>(
+    text: &str,
+    [x, y]: [f64; 2],
+    pixel: f64,
+    color: [f32; 4],
+    transform: Matrix2d,
+    gl: &mut G,
+) {
+    text_pixels(text).into_iter().for_each(|(column, row)| {
+        let square =
+            graphics::rectangle::square(x + column as f64 * pixel, y + row as f64 * pixel, pixel);
+        graphics::rectangle(color, square, transform, gl)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digits_light_their_glyph_pixels() {
+        assert_eq!(
+            text_pixels("1"),
+            vec![
+                (1, 0),
+                (0, 1),
+                (1, 1),
+                (1, 2),
+                (1, 3),
+                (0, 4),
+                (1, 4),
+                (2, 4)
+            ]
+        );
+
+        // * The second glyph starts one blank column after the first
+        let zero: Vec<_> = text_pixels("10")
+            .into_iter()
+            .filter(|&(column, _)| column > GLYPH_WIDTH)
+            .collect();
+        assert_eq!(zero.len(), 12);
+        assert!(zero.contains(&(4, 0)) && zero.contains(&(6, 4)));
+        assert!(!zero.contains(&(5, 2)));
+
+        assert_eq!(text_width("10", 2.0), 14.0);
+        assert_eq!(text_height(2.0), 10.0);
+    }
+}
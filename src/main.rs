@@ -3,260 +3,5563 @@ extern crate graphics;
 extern crate opengl_graphics;
 extern crate piston;
 
+mod assets;
+mod font;
+
 use rand::Rng;
 use std::collections::LinkedList;
 use std::f64;
+use std::fmt;
 
 use glutin_window::GlutinWindow as Window;
-use opengl_graphics::{GlGraphics, OpenGL};
+use graphics::{Transformed, Viewport};
+use opengl_graphics::{GlGraphics, OpenGL, Texture};
 use piston::event_loop::{EventSettings, Events};
-use piston::input::{RenderArgs, RenderEvent, UpdateEvent};
-use piston::window::WindowSettings;
+use piston::input::{CursorEvent, MouseCursorEvent, RenderArgs, RenderEvent, UpdateEvent};
+use piston::window::{AdvancedWindow, WindowSettings};
 use piston::{Button, ButtonEvent, ButtonState, EventLoop, Key};
 
 pub struct App {
-    gl: GlGraphics,
+    layers: Vec<Layer>,
     snake: Snake,
     food: Food,
+    trail: Option<Trail>,
+    patrols: Vec<Patrol>,
+    turns: LinkedList<QueuedTurn>,
+    turn_grace: bool,
+    inverted_controls: bool,
+    // * Co-op steering, the arrow player's turn on even ticks and the WASD player's on odd ones
+    co_op: Option<[Option<Direction>; 2]>,
+    // * Debug measurement of ticks from a key press to its turn, as total and count
+    input_latency: Option<(u64, u32)>,
+    coyote_time: bool,
+    pending_death: bool,
+    history: Option<LinkedList<Snapshot>>,
+    replay: Option<Replay>,
+    death_replay: bool,
+    // * Snapshots kept in `history`, the oldest is dropped past this
+    history_length: usize,
+    burst_seconds: Option<f64>,
+    burst: Option<Burst>,
+    practice: bool,
+    scenario: Option<Snapshot>,
+    theme: Theme,
+    background: Option<Texture>,
+    checkerboard: bool,
+    fog_radius: Option<f64>,
+    show_next_cell: bool,
+    // * Debug aid, the mouse position in window points, the cell under it is labelled in debug mode
+    cursor: Option<[f64; 2]>,
+    // * Debug mode unlocks undo and the board dump's reachability count
+    debug: bool,
+    assist_path: bool,
+    pause_dim: bool,
+    push_food: bool,
+    margin: f64,
+    integer_scale: bool,
+    torus_preview: bool,
     item_size: f64,
     width: f64,
     height: f64,
+    clock: GameClock,
+    // * Frozen before the first move until a direction key is pressed
+    waiting_for_input: bool,
+    ticks: u64,
+    score: Score,
+    scoring: Scoring,
+    ticks_since_food: u64,
+    award: Option<Award>,
+    food_delay: u64,
+    food_respawn_in: u64,
+    // * Ticks food may stay out of the head's reach before it is moved
+    starvation_ticks: Option<u64>,
+    unreachable_ticks: u64,
+    reachable_food: bool,
+    hunger_ticks: Option<u64>,
+    // * Unlike `ticks_since_food` this only restarts on eating, not when delayed food shows up
+    ticks_since_eating: u64,
+    speed_curve: SpeedCurve,
+    speed: u64,
+    show_speed: bool,
+    foods_eaten: u32,
+    goal_foods: Option<u32>,
+    won: bool,
+    death_cause: Option<DeathCause>,
+    lives: Option<u32>,
+    objective: Option<Objective>,
+    objective_done: bool,
+    foods_since_wrap: u32,
+    zen: bool,
+    wrap_warning: bool,
+    reduced_motion: bool,
+    flash_until: f64,
+    milestones: Vec<Milestone>,
+    last_milestone: usize,
+    banner: Option<Banner>,
+    splash_seconds: f64,
+    splash_left: f64,
     ended: bool,
 }
 
 impl App {
-    fn init(opengl: OpenGL, width: f64, height: f64, item_size: f64) -> Self {
-        let food_pos = random_pos(width, height, item_size);
-        let snake_pos = random_pos(width, height, item_size);
+    fn init(width: f64, height: f64, item_size: f64, settings: &Settings) -> Self {
+        // * A background that fails to load falls back to the plain theme colour
+        let background = settings.background.as_ref().and_then(|path| {
+            assets::load_texture(path)
+                .map_err(|err| eprintln!("Warning: background not used, {}", err))
+                .ok()
+        });
+
+        let mut theme = settings.theme;
+        if let Some(padding) = settings.cell_padding {
+            theme.cell_inset = padding;
+        }
+
+        // * The trail is purely cosmetic, so reduced motion turns it off
+        let trail = if settings.trail && !settings.reduced_motion {
+            Some(Trail::new(item_size, &theme))
+        } else {
+            None
+        };
+
+        // * Cells given on the command line may count rows up from the bottom
+        let rows = (height / item_size).floor() as i64;
+        let board_cell = |(x, y): (i64, i64)| {
+            let y = if settings.flip_y { rows - 1 - y } else { y };
+            cell_pos(x, y, item_size)
+        };
+
+        let patrols: Vec<Patrol> = settings
+            .patrols
+            .iter()
+            .map(|&(from, to)| Patrol::new(board_cell(from), board_cell(to), item_size, &theme))
+            .collect();
+
+        // * Like a respawn, the snake never starts on top of a patrol
+        let mut snake_pos = random_pos(width, height, item_size);
+        while patrols.iter().any(|patrol| patrol.position == snake_pos) {
+            snake_pos = random_pos(width, height, item_size);
+        }
+
+        // * Ease the opening by keeping the first food off the head and out of the half the snake
+        // * is heading into
+        let mut food_pos = random_pos(width, height, item_size);
+        while food_pos == snake_pos
+            || is_ahead(
+                &snake_pos,
+                &food_pos,
+                settings.initial_direction,
+                width,
+                height,
+            )
+        {
+            food_pos = random_pos(width, height, item_size);
+        }
+
+        let mut food = Food::new(food_pos, item_size, &theme);
+        if !settings.food_queue.is_empty() {
+            food.queue = settings
+                .food_queue
+                .iter()
+                .map(|&cell| board_cell(cell))
+                .collect();
+            food.cycle_queue = settings.cycle_food;
+            food.next_queued();
+        }
+        food.double = settings.double_food;
+        food.big = settings.big_food;
+        food.pop_in = !settings.reduced_motion;
+
+        let objective = settings.objective.then(Objective::random);
+        let banner = objective.map(|objective| Banner {
+            text: objective.describe(),
+            shown_at: 0.0,
+            seconds: OBJECTIVE_INTRO_SECONDS,
+        });
+
+        let mut milestones = settings.milestones.clone();
+        milestones.sort_by_key(|milestone| milestone.length);
+
+        let mut snake = Snake::new(15.0, snake_pos, &theme);
+        snake.corner_radius = settings.corner_radius;
+        snake.smooth_corners = settings.smooth_corners;
+        snake.length_colors = settings.length_colors;
+        snake.eyes = !settings.no_eyes;
+        snake.max_length = settings.max_length;
+        snake.shrink_on_bite = settings.shrink_on_bite;
+        snake.tail_eats = settings.tail_eats;
+        snake.direction = settings.initial_direction;
 
         App {
-            gl: GlGraphics::new(opengl),
-            snake: Snake::new(15.0, snake_pos),
-            food: Food::new(food_pos, item_size),
+            layers: settings
+                .layers
+                .clone()
+                .unwrap_or_else(|| Layer::ALL.to_vec()),
+            snake,
+            food,
+            trail,
+            patrols,
+            turns: LinkedList::new(),
+            turn_grace: settings.turn_grace,
+            inverted_controls: settings.inverted_controls,
+            co_op: settings.co_op.then_some([None, None]),
+            input_latency: settings.debug.then_some((0, 0)),
+            coyote_time: settings.coyote_time,
+            pending_death: false,
+            history: (settings.debug || (settings.death_replay && !settings.reduced_motion))
+                .then(LinkedList::new),
+            replay: None,
+            death_replay: settings.death_replay,
+            history_length: settings.history_length.unwrap_or(HISTORY_LENGTH),
+            burst_seconds: settings.burst_seconds,
+            burst: None,
+            practice: settings.practice,
+            scenario: None,
+            theme,
+            background,
+            checkerboard: settings.checkerboard,
+            fog_radius: settings.fog_radius,
+            show_next_cell: settings.show_next_cell,
+            cursor: None,
+            debug: settings.debug,
+            assist_path: settings.assist_path,
+            pause_dim: settings.pause_dim,
+            push_food: settings.push_food,
+            margin: settings.margin,
+            integer_scale: settings.integer_scale,
+            torus_preview: settings.torus_preview,
             item_size,
-            width: width as f64,
-            height: height as f64,
+            width,
+            height,
+            clock: GameClock::new(),
+            waiting_for_input: settings.start_on_input,
+            ticks: 0,
+            score: Score::default(),
+            scoring: settings.scoring,
+            ticks_since_food: 0,
+            award: None,
+            food_delay: settings.food_delay,
+            food_respawn_in: 0,
+            starvation_ticks: settings.starvation_ticks,
+            unreachable_ticks: 0,
+            reachable_food: settings.reachable_food,
+            hunger_ticks: settings.hunger_ticks,
+            ticks_since_eating: 0,
+            speed_curve: settings.speed_curve,
+            speed: settings.speed_curve.base,
+            show_speed: settings.show_speed,
+            foods_eaten: 0,
+            goal_foods: settings.goal_foods,
+            won: false,
+            death_cause: None,
+            lives: settings.lives,
+            objective,
+            objective_done: false,
+            foods_since_wrap: 0,
+            zen: settings.zen,
+            wrap_warning: settings.wrap_warning,
+            reduced_motion: settings.reduced_motion,
+            flash_until: 0.0,
+            milestones,
+            last_milestone: 0,
+            banner,
+            splash_seconds: settings.splash_seconds,
+            splash_left: settings.splash_seconds,
             ended: false,
         }
     }
 
-    fn render(&mut self, args: &RenderArgs) {
-        const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+    fn render(&self, gl: &mut GlGraphics, args: &RenderArgs) {
+        let viewport = if self.integer_scale {
+            integer_viewport(args.viewport(), [self.width, self.height], self.margin)
+        } else {
+            board_viewport(args.viewport(), self.margin)
+        };
 
-        self.gl.draw(args.viewport(), |_c, gl| {
-            graphics::clear(WHITE, gl);
-        });
+        for layer in self.drawn_layers() {
+            self.render_layer(gl, layer, args.viewport(), viewport);
+        }
+    }
 
-        self.snake.render(&mut self.gl, args);
-        self.food.render(&mut self.gl, args)
+    // * Layers with something to show this frame, in drawing order, so one that is turned off
+    // * or empty costs no draw calls
+    fn drawn_layers(&self) -> Vec<Layer> {
+        self.layers
+            .iter()
+            .copied()
+            .filter(|&layer| self.draws(layer))
+            .collect()
     }
 
-    fn update(&mut self) {
-        match self
-            .snake
-            .update(&self.food.position, self.width, self.height)
-        {
-            SnakeMoveResult::Ok => return,
-            SnakeMoveResult::Food => {
-                self.food
-                    .reset(self.width, self.height, self.item_size, &self.snake)
-            }
-            SnakeMoveResult::End => self.ended = true,
+    fn draws(&self, layer: Layer) -> bool {
+        match layer {
+            Layer::Background | Layer::Patrols | Layer::Text => true,
+            Layer::Checkerboard => self.checkerboard,
+            Layer::Image => self.background.is_some(),
+            Layer::Trail => self.trail.is_some(),
+            Layer::NextCell => self.show_next_cell,
+            Layer::SafePath => self.assist_path && !self.food.hidden,
+            // * A bursting snake is drawn by its particles instead
+            Layer::Snake => self.burst.is_none(),
+            Layer::Food => !self.food.hidden,
+            Layer::Particles => self.burst.is_some(),
+            Layer::Torus => self.torus_preview,
+            Layer::Fog => self.fog_radius.is_some(),
+            Layer::Warnings => self.wrap_warning || self.replay.is_some(),
+            Layer::Flash => self.flash_until > self.clock.elapsed,
+            Layer::PauseDim => self.pause_dim && self.clock.paused,
+            Layer::Tooltip => self.debug && self.cursor.is_some(),
+            Layer::EndScreen => self.ended,
+            Layer::Splash => self.splash_left > 0.0,
         }
     }
 
-    fn handle_input(&mut self, btn: &Button) {
-        let last_direction = self.snake.direction.clone();
+    // * `window` is the whole window, `viewport` the board inside it, only called for layers
+    // * that `draws` says have something to show
+    fn render_layer(
+        &self,
+        gl: &mut GlGraphics,
+        layer: Layer,
+        window: Viewport,
+        viewport: Viewport,
+    ) {
+        let board = [0.0, 0.0, self.width, self.height];
+
+        match layer {
+            Layer::Background => {
+                let background = self.theme.background;
+
+                // * Clearing ignores the viewport, so this also paints the margin
+                gl.draw(viewport, |_c, gl| {
+                    graphics::clear(background, gl);
+                });
+            }
+            Layer::Checkerboard => self.render_checkerboard(gl, viewport),
+            Layer::Image => {
+                if let Some(texture) = &self.background {
+                    gl.draw(viewport, |c, gl| {
+                        graphics::Image::new().rect(board).draw(
+                            texture,
+                            &c.draw_state,
+                            c.transform,
+                            gl,
+                        );
+                    });
+                }
+            }
+            Layer::Trail => {
+                if let Some(trail) = &self.trail {
+                    trail.render(gl, viewport);
+                }
+            }
+            Layer::NextCell => self.render_next_cell(gl, viewport),
+            Layer::SafePath => self.render_safe_path(gl, viewport),
+            Layer::Snake => self.snake.render(gl, viewport),
+            Layer::Food => self.food.render(gl, viewport, self.clock.elapsed),
+            Layer::Patrols => {
+                for patrol in &self.patrols {
+                    patrol.render(gl, viewport);
+                }
+            }
+            Layer::Particles => {
+                if let Some(burst) = &self.burst {
+                    burst.render(gl, viewport, self.clock.elapsed);
+                }
+            }
+            Layer::Torus => self.render_torus_preview(gl, viewport),
+            Layer::Fog => {
+                if let Some(radius) = self.fog_radius {
+                    self.render_fog(gl, radius, viewport);
+                }
+            }
+            Layer::Warnings => {
+                if self.wrap_warning {
+                    self.render_wrap_warning(gl, viewport);
+                }
+                if let Some(replay) = &self.replay {
+                    let color = self.theme.hazard;
+                    let square = cell_square(&replay.fatal, self.item_size, 0.0);
+
+                    gl.draw(viewport, |c, gl| {
+                        graphics::rectangle(color, square, c.transform, gl);
+                    });
+                }
+            }
+            Layer::Flash => {
+                let flash_left = self.flash_until - self.clock.elapsed;
+                let [r, g, b, _] = self.theme.snake;
+                let alpha = 0.4 * (flash_left / RESPAWN_FLASH_SECONDS) as f32;
+
+                gl.draw(viewport, |c, gl| {
+                    graphics::rectangle([r, g, b, alpha], board, c.transform, gl);
+                });
+            }
+            Layer::PauseDim => {
+                // * Reduced motion dims at once instead of fading in
+                let strength = if self.reduced_motion {
+                    1.0
+                } else {
+                    (self.clock.paused_for / PAUSE_DIM_FADE_SECONDS).min(1.0) as f32
+                };
 
-        self.snake.direction = match btn {
-            &Button::Keyboard(Key::Up) if last_direction != Direction::Down => Direction::Up,
-            &Button::Keyboard(Key::Down) if last_direction != Direction::Up => Direction::Down,
-            &Button::Keyboard(Key::Left) if last_direction != Direction::Right => Direction::Left,
-            &Button::Keyboard(Key::Right) if last_direction != Direction::Left => Direction::Right,
-            _ => last_direction,
+                gl.draw(viewport, |c, gl| {
+                    graphics::rectangle(
+                        [0.0, 0.0, 0.0, PAUSE_DIM_ALPHA * strength],
+                        board,
+                        c.transform,
+                        gl,
+                    );
+                });
+            }
+            Layer::Text => self.render_text(gl, viewport),
+            Layer::Tooltip => {
+                if let Some(cursor) = self.cursor {
+                    self.render_tooltip(gl, board_point(cursor, window, viewport), viewport);
+                }
+            }
+            Layer::EndScreen => self.render_end_screen(gl, viewport),
+            Layer::Splash => self.render_splash(gl, viewport),
         }
     }
-}
 
-#[derive(Clone, PartialEq)]
-enum Direction {
-    Left,
-    Right,
-    Up,
-    Down,
-}
-
-struct Position {
-    x: f64,
-    y: f64,
-}
+    fn render_checkerboard(&self, gl: &mut GlGraphics, viewport: Viewport) {
+        let theme = self.theme;
+        let columns = (self.width / self.item_size).floor() as i64;
+        let rows = (self.height / self.item_size).floor() as i64;
+        let item_size = self.item_size;
 
-enum SnakeMoveResult {
-    Ok,
-    Food,
-    End,
-}
+        gl.draw(viewport, |c, gl| {
+            for y in 0..rows {
+                for x in 0..columns {
+                    let square = cell_square(&cell_pos(x, y, item_size), item_size, 0.0);
+                    graphics::rectangle(theme.checker_shade(x, y), square, c.transform, gl);
+                }
+            }
+        });
+    }
 
-struct Snake {
-    body: LinkedList<Position>,
-    size: f64,
-    color: [f32; 4],
-    direction: Direction,
-}
+    fn render_next_cell(&self, gl: &mut GlGraphics, viewport: Viewport) {
+        let Some(head) = self.snake.body.front() else {
+            return;
+        };
+        let next = next_cell(
+            head,
+            self.snake.direction,
+            self.width,
+            self.height,
+            self.item_size,
+        );
+        let [r, g, b, _] = self.theme.snake;
+        let square = cell_square(&next, self.item_size, self.theme.cell_inset);
 
-impl Snake {
-    fn new(size: f64, initial_pos: Position) -> Self {
-        Snake {
-            body: LinkedList::from([initial_pos]),
-            size,
-            color: [1.0, 0.0, 0.0, 1.0],
-            direction: Direction::Right,
-        }
+        gl.draw(viewport, |c, gl| {
+            graphics::rectangle([r, g, b, 0.25], square, c.transform, gl);
+        });
     }
 
-    fn render(&self, gl: &mut GlGraphics, args: &RenderArgs) {
-        let squares: Vec<graphics::types::Rectangle> = self
+    // * Faint copies of whatever sits along the far edges, drawn in the margin where it would come round
+    fn render_torus_preview(&self, gl: &mut GlGraphics, viewport: Viewport) {
+        let scale = viewport.draw_size[0] as f64 / viewport.window_size[0];
+        let inset = (self.margin * scale).round() as i32;
+        let [x, y, width, height] = viewport.rect;
+        let outer = Viewport {
+            rect: [x - inset, y - inset, width + inset * 2, height + inset * 2],
+            ..viewport
+        };
+
+        let faint = |[r, g, b, _]: [f32; 4]| [r, g, b, 0.35];
+        let cells: Vec<(Position, [f32; 4])> = self
+            .snake
             .body
             .iter()
-            .map(|pos| graphics::rectangle::square(pos.x, pos.y, self.size))
+            .map(|pos| (*pos, faint(self.theme.snake)))
+            .chain(
+                self.food
+                    .cells()
+                    .into_iter()
+                    .filter(|_| !self.food.hidden)
+                    .map(|pos| (pos, faint(self.theme.food))),
+            )
+            .chain(
+                self.patrols
+                    .iter()
+                    .map(|patrol| (patrol.position, faint(self.theme.hazard))),
+            )
             .collect();
+        let (margin, item_size) = (self.margin, self.item_size);
+        let (board_width, board_height) = (self.width, self.height);
 
-        gl.draw(args.viewport(), |c, gl| {
-            let transform = c.transform;
+        gl.draw(outer, |c, gl| {
+            let transform = c.transform.trans(margin, margin);
+            for (pos, color) in &cells {
+                for ghost in torus_ghosts(pos, board_width, board_height, margin) {
+                    let square = cell_square(&ghost, item_size, 0.0);
+                    graphics::rectangle(*color, square, transform, gl);
+                }
+            }
+        });
+    }
 
+    fn render_safe_path(&self, gl: &mut GlGraphics, viewport: Viewport) {
+        let [r, g, b, _] = self.theme.food;
+        let squares: Vec<_> = self
+            .safe_path()
+            .iter()
+            .take(SAFE_PATH_CELLS)
+            .map(|pos| cell_square(pos, self.item_size, self.theme.cell_inset))
+            .collect();
+
+        gl.draw(viewport, |c, gl| {
             squares
                 .into_iter()
-                .for_each(|square| graphics::rectangle(self.color, square, transform, gl))
+                .for_each(|square| graphics::rectangle([r, g, b, 0.25], square, c.transform, gl));
         });
     }
-    fn update(&mut self, food_pos: &Position, width: f64, height: f64) -> SnakeMoveResult {
-        let mut new_x = self.body.front().expect("Snake has no body").x;
-        let mut new_y = self.body.front().expect("Snake has no body").y;
-        match self.direction {
-            Direction::Left => new_x -= self.size,
-            Direction::Right => new_x += self.size,
-            Direction::Up => new_y -= self.size,
-            Direction::Down => new_y += self.size,
+
+    // * Column and row under a point in board coordinates, if it is on the board
+    fn hovered_cell(&self, [x, y]: [f64; 2]) -> Option<(i64, i64)> {
+        if x < 0.0 || y < 0.0 || x >= self.width || y >= self.height {
+            return None;
         }
+        Some((
+            (x / self.item_size).floor() as i64,
+            (y / self.item_size).floor() as i64,
+        ))
+    }
 
-        let mut new_pos = Position { x: new_x, y: new_y };
+    // * Debug aid, names the cell under the mouse and what is on it
+    fn render_tooltip(&self, gl: &mut GlGraphics, [x, y]: [f64; 2], viewport: Viewport) {
+        let Some((column, row)) = self.hovered_cell([x, y]) else {
+            return;
+        };
+        let text = format!(
+            "{},{} {}",
+            column,
+            row,
+            self.cell_contents(&cell_pos(column, row, self.item_size))
+        );
 
-        if new_pos.x >= width {
-            new_pos.x = 0.0;
-        } else if new_pos.x < 0.0 {
-            new_pos.x = width - self.size;
-        }
+        // * Kept on the board when the cursor is near the right or bottom edge
+        let color = self.theme.text;
+        let left = (x + 8.0).min(self.width - font::text_width(&text, TEXT_PIXEL) - 2.0);
+        let top = (y + 8.0).min(self.height - font::text_height(TEXT_PIXEL) - 2.0);
 
-        if new_pos.y >= height {
-            new_pos.y = 0.0;
-        } else if new_pos.y < 0.0 {
-            new_pos.y = height - self.size;
-        }
+        gl.draw(viewport, |c, gl| {
+            font::draw_text(&text, [left, top], TEXT_PIXEL, color, c.transform, gl);
+        });
+    }
 
-        if self.self_collision(&new_pos) {
-            return SnakeMoveResult::End;
-        } else {
-            self.body.push_front(new_pos);
+    fn render_fog(&self, gl: &mut GlGraphics, radius: f64, viewport: Viewport) {
+        let Some(head) = self.snake.body.front() else {
+            return;
+        };
+        let [r, g, b, _] = self.theme.background;
+        let columns = (self.width / self.item_size).floor() as i64;
+        let rows = (self.height / self.item_size).floor() as i64;
+        let head_x = (head.x / self.item_size).round() as i64;
+        let head_y = (head.y / self.item_size).round() as i64;
+        let item_size = self.item_size;
 
-            if new_x == food_pos.x && new_y == food_pos.y {
-                return SnakeMoveResult::Food;
-            } else {
-                self.body.pop_back().unwrap();
-                return SnakeMoveResult::Ok;
+        gl.draw(viewport, |c, gl| {
+            for y in 0..rows {
+                for x in 0..columns {
+                    // * The board wraps, so distance is measured the short way round
+                    let dx = (x - head_x).abs().min(columns - (x - head_x).abs());
+                    let dy = (y - head_y).abs().min(rows - (y - head_y).abs());
+                    let distance = ((dx * dx + dy * dy) as f64).sqrt();
+
+                    let alpha = fog_alpha(distance, radius);
+                    if alpha > 0.0 {
+                        let square = cell_square(&cell_pos(x, y, item_size), item_size, 0.0);
+                        graphics::rectangle([r, g, b, alpha], square, c.transform, gl);
+                    }
+                }
             }
-        }
+        });
     }
 
-    fn self_collision(&self, new_pos: &Position) -> bool {
-        self.body
-            .iter()
-            .any(|pos| pos.x == new_pos.x && pos.y == new_pos.y)
+    fn render_wrap_warning(&self, gl: &mut GlGraphics, viewport: Viewport) {
+        if let Some((exit, entry)) = self.upcoming_wrap() {
+            // * Reduced motion gets a steady tint instead of the flashing
+            let strength = if self.reduced_motion {
+                0.6
+            } else {
+                0.5 + 0.5 * (self.clock.elapsed * f64::consts::TAU * 4.0).sin() as f32
+            };
+            let [r, g, b, _] = self.theme.hazard;
+            let color = [r, g, b, 0.5 * strength];
+            let squares = [exit, entry].map(|pos| cell_square(&pos, self.item_size, 0.0));
+
+            gl.draw(viewport, |c, gl| {
+                squares
+                    .into_iter()
+                    .for_each(|square| graphics::rectangle(color, square, c.transform, gl))
+            });
+        }
     }
-}
 
-struct Food {
-    position: Position,
-    size: f64,
-    color: [f32; 4],
-}
+    fn render_text(&self, gl: &mut GlGraphics, viewport: Viewport) {
+        let color = self.theme.text;
+        let score = self.displayed_score().to_string();
+        let (width, height) = (self.width, self.height);
+        let paused = self.clock.paused;
+        let waiting = self.waiting_for_input;
+        let lives = self.lives.map(|lives| format!("Lives: {}", lives));
+        let steering = self
+            .co_op
+            .map(|_| format!("P{} steers", (self.ticks + 1) % 2 + 1));
+        let award = self.award.as_ref().and_then(|award| {
+            let shown_for = self.clock.elapsed - award.shown_at;
+            (shown_for < AWARD_SECONDS).then(|| {
+                let t = shown_for / AWARD_SECONDS;
+                let rise = if self.reduced_motion {
+                    0.0
+                } else {
+                    t * self.item_size
+                };
+                let [r, g, b, _] = color;
+                (
+                    format!("+{}", award.points),
+                    [award.position.x, award.position.y - rise],
+                    [r, g, b, (1.0 - t) as f32],
+                )
+            })
+        });
+        let cells_per_second = self.speed_readout();
+        let latency = self
+            .input_latency
+            .filter(|&(_, count)| count > 0)
+            .map(|(total, count)| {
+                let ticks = total as f64 / count as f64;
+                format!(
+                    "Input lag: {:.1} ticks ({:.0}ms)",
+                    ticks,
+                    ticks * 1000.0 / self.speed as f64
+                )
+            });
+        // * Shrinks towards the next lost segment
+        let hunger = self.hunger_ticks.map(|hunger| {
+            let left = hunger - self.ticks_since_eating % hunger;
+            [0.0, 0.0, width * left as f64 / hunger as f64, 2.0]
+        });
+        let banner = self.banner.as_ref().and_then(|banner| {
+            let shown_for = self.clock.elapsed - banner.shown_at;
+            (shown_for < banner.seconds).then(|| {
+                // * Fades out over the last second
+                let [r, g, b, _] = color;
+                let alpha = (banner.seconds - shown_for).min(1.0) as f32;
+                (banner.text.as_str(), [r, g, b, alpha])
+            })
+        });
 
-impl Food {
-    fn new(initial_pos: Position, item_size: f64) -> Self {
-        Food {
-            position: initial_pos,
-            size: item_size,
-            color: [0.0, 1.0, 0.0, 1.0],
-        }
+        gl.draw(viewport, |c, gl| {
+            if let Some(meter) = hunger {
+                graphics::rectangle(color, meter, c.transform, gl);
+            }
+
+            font::draw_text(&score, [4.0, 4.0], TEXT_PIXEL, color, c.transform, gl);
+
+            if let Some((text, position, color)) = &award {
+                font::draw_text(text, *position, TEXT_PIXEL, *color, c.transform, gl);
+            }
+
+            if let Some(text) = &cells_per_second {
+                let x = width - 4.0 - font::text_width(text, TEXT_PIXEL);
+                let y = height - 4.0 - font::text_height(TEXT_PIXEL);
+                font::draw_text(text, [x, y], TEXT_PIXEL, color, c.transform, gl);
+            }
+
+            if let Some(latency) = &latency {
+                let y = height - 4.0 - font::text_height(TEXT_PIXEL);
+                font::draw_text(latency, [4.0, y], TEXT_PIXEL, color, c.transform, gl);
+            }
+
+            if let Some(lives) = &lives {
+                let x = width - 4.0 - font::text_width(lives, TEXT_PIXEL);
+                font::draw_text(lives, [x, 4.0], TEXT_PIXEL, color, c.transform, gl);
+            }
+
+            if let Some(steering) = &steering {
+                let x = (width - font::text_width(steering, TEXT_PIXEL)) / 2.0;
+                font::draw_text(steering, [x, 4.0], TEXT_PIXEL, color, c.transform, gl);
+            }
+
+            if let Some((text, color)) = banner {
+                let pixel = TEXT_PIXEL * 2.0;
+                let x = (width - font::text_width(text, pixel)) / 2.0;
+                let y = height / 4.0;
+                font::draw_text(text, [x, y], pixel, color, c.transform, gl);
+            }
+
+            if waiting {
+                let message = "Press a direction to start";
+                let x = (width - font::text_width(message, TEXT_PIXEL)) / 2.0;
+                let y = (height - font::text_height(TEXT_PIXEL)) / 2.0;
+                font::draw_text(message, [x, y], TEXT_PIXEL, color, c.transform, gl);
+            }
+
+            if paused {
+                let message = "PAUSED";
+                let pixel = TEXT_PIXEL * 2.0;
+                let x = (width - font::text_width(message, pixel)) / 2.0;
+                let y = (height - font::text_height(pixel)) / 2.0;
+                font::draw_text(message, [x, y], pixel, color, c.transform, gl);
+            }
+        });
     }
-    fn render(&self, gl: &mut GlGraphics, args: &RenderArgs) {
-        let square = graphics::rectangle::square(self.position.x, self.position.y, self.size);
 
-        gl.draw(args.viewport(), |c, gl| {
-            let transform = c.transform;
-            graphics::rectangle(self.color, square, transform, gl)
+    fn render_end_screen(&self, gl: &mut GlGraphics, viewport: Viewport) {
+        let [r, g, b, _] = self.theme.background;
+        let color = self.theme.text;
+        let board = [0.0, 0.0, self.width, self.height];
+        let width = self.width;
+        let lines = self.stats().lines();
+        let line_height = font::text_height(TEXT_PIXEL) * 2.0;
+        let top = (self.height - line_height * lines.len() as f64) / 2.0;
+
+        gl.draw(viewport, |c, gl| {
+            graphics::rectangle([r, g, b, 0.85], board, c.transform, gl);
+
+            for (i, line) in lines.iter().enumerate() {
+                let x = (width - font::text_width(line, TEXT_PIXEL)) / 2.0;
+                let y = top + i as f64 * line_height;
+                font::draw_text(line, [x, y], TEXT_PIXEL, color, c.transform, gl);
+            }
         });
     }
 
-    fn reset(&mut self, width: f64, height: f64, item_size: f64, snake: &Snake) {
-        // TODO Check against snake body
-        let new_pos = random_pos(width, height, item_size);
-        if snake.self_collision(&new_pos) {
-            self.reset(width, height, item_size, snake);
+    fn render_splash(&self, gl: &mut GlGraphics, viewport: Viewport) {
+        let board = [0.0, 0.0, self.width, self.height];
+        let background = self.theme.background;
+        let [r, g, b, _] = self.theme.text;
+        // * Fades in over the first half, reduced motion shows it at full strength straight away
+        let shown_for = self.splash_seconds - self.splash_left;
+        let alpha = if self.reduced_motion {
+            1.0
         } else {
-            self.position = new_pos;
-        }
+            (shown_for * 2.0 / self.splash_seconds).min(1.0) as f32
+        };
+        let pixel = TEXT_PIXEL * 4.0;
+        let x = (self.width - font::text_width(SPLASH_TITLE, pixel)) / 2.0;
+        let y = (self.height - font::text_height(pixel)) / 2.0;
+
+        gl.draw(viewport, |c, gl| {
+            graphics::rectangle(background, board, c.transform, gl);
+            font::draw_text(
+                SPLASH_TITLE,
+                [x, y],
+                pixel,
+                [r, g, b, alpha],
+                c.transform,
+                gl,
+            );
+        });
     }
-}
 
-fn main() {
-    let opengl = OpenGL::V3_2;
+    fn update(&mut self, dt: f64) -> Result<(), GameError> {
+        // * The splash counts down in real time and keeps the game clock at zero until it's gone
+        if self.splash_left > 0.0 {
+            self.splash_left = (self.splash_left - dt).max(0.0);
+            return Ok(());
+        }
+        if self.waiting_for_input {
+            // * Nothing moves yet, but a pause still times its dimming
+            if self.clock.paused {
+                self.clock.advance(dt);
+            }
+            return Ok(());
+        }
+        self.clock.advance(dt);
+        if self.ended || self.clock.paused || self.clock.hold > 0.0 {
+            return Ok(());
+        }
 
-    const WIDTH: f64 = 300.0;
-    const HEIGHT: f64 = 300.0;
-    const ITEM_SIZE: f64 = 15.0;
+        if let Some(replay) = &mut self.replay {
+            if self.clock.elapsed >= replay.next_frame_at {
+                replay.next_frame_at += REPLAY_FRAME_SECONDS;
+                match replay.frames.pop_front() {
+                    Some(frame) => self.restore(frame),
+                    None => self.ended = true,
+                }
+            }
+            return Ok(());
+        }
+        if let Some(burst) = &self.burst {
+            if self.clock.elapsed >= burst.started_at + burst.seconds {
+                self.burst = None;
+                self.ended = true;
+            }
+            return Ok(());
+        }
 
-    // * Creates a window
-    let mut window: Window = WindowSettings::new("Snake game", [WIDTH, HEIGHT])
-        .graphics_api(opengl)
-        .exit_on_esc(true)
-        .build()
-        .unwrap();
+        self.ticks += 1;
+        self.ticks_since_food += 1;
+        self.ticks_since_eating += 1;
 
-    let mut app = App::init(opengl, WIDTH, HEIGHT, ITEM_SIZE);
-    let mut events = Events::new(EventSettings::new()).ups(10);
-    while let Some(e) = events.next(&mut window) {
-        if app.ended {
-            break;
+        // * Recorded before anything in this tick changes, food countdown included
+        if self.history.is_some() {
+            let snapshot = self.snapshot();
+            if let Some(history) = &mut self.history {
+                history.push_front(snapshot);
+                if history.len() > self.history_length {
+                    history.pop_back();
+                }
+            }
         }
-        if let Some(args) = e.render_args() {
-            app.render(&args);
+
+        // * Delayed food always comes back, so the board is never empty for long
+        if self.food.hidden {
+            self.food_respawn_in = self.food_respawn_in.saturating_sub(1);
+            if self.food_respawn_in == 0 {
+                self.respawn_food();
+            }
+        }
+
+        if let Some(steer) = &mut self.co_op {
+            let player = (self.ticks % 2) as usize;
+            if let Some(direction) = steer[player].take() {
+                if direction != self.snake.direction.opposite() || self.snake.body.len() == 1 {
+                    self.snake.direction = direction;
+                }
+            }
+        } else if let Some(turn) = self.turns.front_mut() {
+            if turn.delay > 0 {
+                turn.delay -= 1;
+            } else {
+                // * The snake may have grown since a reversal was queued
+                if turn.direction != self.snake.direction.opposite() || self.snake.body.len() == 1 {
+                    self.snake.direction = turn.direction;
+                    if let Some((total, count)) = &mut self.input_latency {
+                        *total += self.ticks - turn.queued_at;
+                        *count += 1;
+                    }
+                }
+                self.turns.pop_front();
+            }
         }
 
-        if let Some(_args) = e.update_args() {
-            app.update();
+        // * Patrols move first so one stepping onto the head is caught before the head moves away
+        self.patrols.iter_mut().for_each(|patrol| patrol.update());
+        if self.patrol_collision()? {
+            let head = *self.snake.body.front().ok_or(GameError::EmptyBody)?;
+            self.game_over(head, DeathCause::Patrol);
+            return Ok(());
         }
-        if let Some(args) = e.button_args() {
-            if args.state == ButtonState::Press {
-                app.handle_input(&args.button);
+
+        if self.starve() {
+            let head = *self.snake.body.front().ok_or(GameError::EmptyBody)?;
+            self.game_over(head, DeathCause::Starved);
+            return Ok(());
+        }
+
+        let tail = *self.snake.body.back().ok_or(GameError::EmptyBody)?;
+        let wraps = self.snake.wraps;
+        let before = self.coyote_time.then(|| self.snake.body.clone());
+
+        self.guard_against_starvation()?;
+        if self.push_food {
+            self.push_food_ahead()?;
+        }
+
+        let mut result = self.snake.update(&self.food, self.width, self.height)?;
+        if self.patrol_collision()? {
+            let head = *self.snake.body.front().ok_or(GameError::EmptyBody)?;
+            result = SnakeMoveResult::End(head, DeathCause::Patrol);
+        }
+
+        // * Coyote time holds the snake back for one tick, a turn in that tick can still save it
+        if let SnakeMoveResult::End(..) = result {
+            if let Some(body) = before.filter(|_| !self.pending_death) {
+                self.snake.body = body;
+                self.pending_death = true;
+                return Ok(());
+            }
+        }
+        self.pending_death = false;
+
+        if let Some(trail) = &mut self.trail {
+            // * The tail only moves off its cell when the snake didn't grow
+            let vacated = !self.snake.body.contains(&tail);
+            trail.update(vacated.then_some(tail));
+        }
+
+        if self.snake.wraps > wraps {
+            self.foods_since_wrap = 0;
+            self.score.wrap += self.scoring.wrap_points;
+        }
+        self.check_objective();
+
+        match result {
+            SnakeMoveResult::Ok => {}
+            SnakeMoveResult::Food(cell) => {
+                // * Both cells of a double food have to be eaten before it scores
+                if !self.food.eat(&cell) {
+                    return Ok(());
+                }
+
+                // * Big food counts as several foods' worth of points and growth
+                let multiplier = if self.food.block {
+                    BIG_FOOD_MULTIPLIER
+                } else {
+                    1
+                };
+                self.snake.pending_growth += multiplier as usize - 1;
+
+                let before = self.score.total();
+                self.score.food += self.scoring.base * multiplier;
+                self.score.length += self.scoring.length_bonus * self.snake.body.len() as u32;
+                self.score.speed += self.scoring.speed_bonus * self.speed as u32;
+                self.score.quick += self.scoring.quick_award(self.ticks_since_food);
+                self.ticks_since_food = 0;
+                self.ticks_since_eating = 0;
+                self.award = Some(Award {
+                    points: self.score.total() - before,
+                    position: cell,
+                    shown_at: self.clock.elapsed,
+                });
+                self.foods_eaten += 1;
+                self.speed = self.speed_curve.speed_at(self.foods_eaten);
+                self.foods_since_wrap += 1;
+                self.check_milestone();
+
+                if self.goal_foods == Some(self.foods_eaten) {
+                    self.won = true;
+                    self.ended = true;
+                } else if self.food_delay > 0 {
+                    self.food.hidden = true;
+                    self.food_respawn_in = self.food_delay;
+                } else {
+                    self.respawn_food();
+                }
+            }
+            SnakeMoveResult::Bitten(lost) => {
+                // * The lost segments take the food points they were worth with them
+                let points = self.scoring.base * lost as u32;
+                self.score.food = self.score.food.saturating_sub(points);
             }
+            SnakeMoveResult::End(fatal, cause) => self.game_over(fatal, cause),
         }
+
+        Ok(())
     }
-}
 
-fn random_pos(width: f64, height: f64, item_size: f64) -> Position {
-    let mut rng = rand::thread_rng();
-    let grid_size_height = (height / item_size) - 1.0;
-    let grid_size_height = grid_size_height.floor() as i64;
-    let grid_size_width = (width / item_size) - 1.0;
-    let grid_size_width = grid_size_width.floor() as i64;
-    let rand_x = rng.gen_range(0..grid_size_width) as f64;
-    let rand_y = rng.gen_range(0..grid_size_height) as f64;
+    // * Edge cell the head is about to leave through and the cell on the far side it will enter
+    fn upcoming_wrap(&self) -> Option<(Position, Position)> {
+        let head = self.snake.body.front()?;
+        let last_x = self.width - self.item_size;
+        let last_y = self.height - self.item_size;
+        let warning = WRAP_WARNING_CELLS as f64 * self.item_size;
 
-    let rand_x = rand_x * item_size;
-    let rand_y = rand_y * item_size;
+        let (exit, entry) = match self.snake.direction {
+            Direction::Left => (0.0, last_x),
+            Direction::Right => (last_x, 0.0),
+            Direction::Up => (0.0, last_y),
+            Direction::Down => (last_y, 0.0),
+        };
 
-    Position {
-        x: rand_x,
-        y: rand_y,
+        match self.snake.direction {
+            Direction::Left | Direction::Right if (exit - head.x).abs() < warning => Some((
+                Position { x: exit, y: head.y },
+                Position {
+                    x: entry,
+                    y: head.y,
+                },
+            )),
+            Direction::Up | Direction::Down if (exit - head.y).abs() < warning => Some((
+                Position { x: head.x, y: exit },
+                Position {
+                    x: head.x,
+                    y: entry,
+                },
+            )),
+            _ => None,
+        }
+    }
+
+    fn game_over(&mut self, fatal: Position, cause: DeathCause) {
+        self.death_cause = Some(cause);
+
+        // * Practice never ends on a crash once a position is saved, it goes straight back to it
+        if let (true, Some(scenario)) = (self.practice, self.scenario.clone()) {
+            self.restore(scenario);
+            self.turns.clear();
+            return;
+        }
+
+        // * Zen mode forgives every crash, so it never uses up lives
+        if let (Some(lives), false) = (&mut self.lives, self.zen) {
+            *lives -= 1;
+            if *lives > 0 {
+                self.respawn();
+                return;
+            }
+        }
+
+        if !self.zen {
+            // * Play the approach back slowly before ending, using the recorded history
+            if self.death_replay && !self.reduced_motion {
+                if let Some(history) = self.history.take() {
+                    self.replay = Some(Replay {
+                        frames: history.into_iter().rev().collect(),
+                        fatal,
+                        next_frame_at: self.clock.elapsed,
+                    });
+                    return;
+                }
+            }
+
+            if let (Some(seconds), false) = (self.burst_seconds, self.reduced_motion) {
+                self.burst = Some(Burst::new(&self.snake, self.clock.elapsed, seconds));
+                return;
+            }
+
+            self.ended = true;
+            return;
+        }
+
+        // * Zen mode forgives the crash
+        self.respawn();
+    }
+
+    // * The snake starts over at length 1 somewhere safe and keeps its score
+    fn respawn(&mut self) {
+        let mut pos = random_pos(self.width, self.height, self.item_size);
+        while self.food.covers(&pos) || self.patrols.iter().any(|patrol| patrol.position == pos) {
+            pos = random_pos(self.width, self.height, self.item_size);
+        }
+
+        self.snake.body = LinkedList::from([pos]);
+        self.snake.pending_growth = 0;
+        self.ticks_since_food = 0;
+        self.ticks_since_eating = 0;
+        self.turns.clear();
+        if let Some(steer) = &mut self.co_op {
+            *steer = [None, None];
+        }
+        if !self.reduced_motion {
+            self.flash_until = self.clock.elapsed + RESPAWN_FLASH_SECONDS;
+        }
+    }
+
+    // * Shows a banner for the longest milestone the snake has just reached, each one only once
+    fn check_milestone(&mut self) {
+        let length = self.snake.body.len();
+        let Some(milestone) =
+            self.milestones.iter().rev().find(|milestone| {
+                milestone.length > self.last_milestone && milestone.length <= length
+            })
+        else {
+            return;
+        };
+
+        self.last_milestone = milestone.length;
+        self.banner = Some(Banner {
+            text: format!("Length {}!", milestone.length),
+            shown_at: self.clock.elapsed,
+            seconds: MILESTONE_BANNER_SECONDS,
+        });
+        if milestone.major {
+            self.clock.hold = MILESTONE_HOLD_SECONDS;
+        }
+    }
+
+    // * Food in the way gets pushed a cell further, it's only eaten once it can't move
+    fn push_food_ahead(&mut self) -> Result<(), GameError> {
+        let head = self.snake.body.front().ok_or(GameError::EmptyBody)?;
+        let size = self.item_size;
+        let ahead = next_cell(head, self.snake.direction, self.width, self.height, size);
+        if !self.food.covers(&ahead) || self.food.block || self.food.pair.is_some() {
+            return Ok(());
+        }
+
+        let (dx, dy) = match self.snake.direction {
+            Direction::Left => (-size, 0.0),
+            Direction::Right => (size, 0.0),
+            Direction::Up => (0.0, -size),
+            Direction::Down => (0.0, size),
+        };
+        let target = Position {
+            x: ahead.x + dx,
+            y: ahead.y + dy,
+        };
+        // * Food doesn't wrap, the board edges stop it like walls
+        let on_board =
+            target.x >= 0.0 && target.x < self.width && target.y >= 0.0 && target.y < self.height;
+        let blocked = self.snake.self_collision(&target)
+            || self.patrols.iter().any(|patrol| patrol.position == target);
+
+        if on_board && !blocked {
+            self.food.position = target;
+        }
+        Ok(())
+    }
+
+    // * Rolls a new spot for the food, clear of the snake and the patrols
+    fn reset_food(&mut self) {
+        let patrols: Vec<Position> = self.patrols.iter().map(|patrol| patrol.position).collect();
+        self.food.reset(
+            self.width,
+            self.height,
+            self.item_size,
+            &self.snake,
+            &patrols,
+        );
+    }
+
+    fn respawn_food(&mut self) {
+        // * Food from --food is placed exactly as asked, rerolling would pop the rest of the queue
+        let queued = !self.food.queue.is_empty();
+        self.reset_food();
+        if self.reachable_food && !queued {
+            self.reroll_unreachable_food();
+        }
+        self.food.hidden = false;
+        self.food.spawned_at = self.clock.elapsed;
+        // * The quick bonus counts from when the food shows up
+        self.ticks_since_food = 0;
+    }
+
+    fn check_objective(&mut self) {
+        let Some(objective) = self.objective.filter(|_| !self.objective_done) else {
+            return;
+        };
+
+        let done = match objective {
+            Objective::ReachLength(length) => self.snake.body.len() >= length,
+            Objective::Survive(seconds) => self.clock.elapsed >= seconds,
+            Objective::EatWithoutWrapping(foods) => self.foods_since_wrap >= foods,
+        };
+        if done {
+            self.objective_done = true;
+            self.score.objective += OBJECTIVE_BONUS;
+            self.banner = Some(Banner {
+                text: "Challenge done!".to_string(),
+                shown_at: self.clock.elapsed,
+                seconds: MILESTONE_BANNER_SECONDS,
+            });
+        }
+    }
+
+    fn stats(&self) -> GameStats {
+        GameStats {
+            won: self.won,
+            score: self.displayed_score(),
+            length: self.snake.body.len(),
+            seconds: self.clock.elapsed,
+            foods: self.foods_eaten,
+            wraps: self.snake.wraps,
+            cause: self.death_cause,
+        }
+    }
+
+    fn displayed_score(&self) -> u32 {
+        match self.scoring.mode {
+            ScoreMode::FoodCount => self.foods_eaten,
+            // * The snake starts out as a lone head
+            ScoreMode::Length => self.snake.body.len() as u32 - 1,
+            ScoreMode::Formula => self.score.total(),
+        }
+    }
+
+    // * --max-ticks stops the run here, paused and replay frames never count as ticks
+    fn out_of_ticks(&self, max: u64) -> bool {
+        self.ticks >= max
+    }
+
+    fn speed_readout(&self) -> Option<String> {
+        // * The snake moves one cell per tick
+        self.show_speed
+            .then(|| format!("{:.1} cells/s", self.speed as f64))
+    }
+
+    fn title(&self) -> String {
+        let mut title = format!(
+            "Snake game - Score: {} - Time: {}s",
+            self.displayed_score(),
+            self.clock.elapsed.floor()
+        );
+        if let Some(goal) = self.goal_foods {
+            title += &format!(" - Food left: {}", goal - self.foods_eaten);
+        }
+        if self.scoring.wrap_points != 0 {
+            title += &format!(" - Wraps: {}", self.snake.wraps);
+        }
+        if self.speed_curve.ramps() {
+            title += &format!(" - Speed: {}", self.speed);
+        }
+        if let (Some(objective), false) = (self.objective, self.objective_done) {
+            title += &format!(" - Challenge: {}", objective.describe());
+        }
+        if self.clock.paused {
+            title += " - Paused";
+        }
+        title
+    }
+
+    // * Plain text picture of the board, # snake, O food, X obstacle, . empty
+    fn to_ascii(&self) -> String {
+        let columns = (self.width / self.item_size).floor() as usize;
+        let rows = (self.height / self.item_size).floor() as usize;
+        let mut grid = vec![vec!['.'; columns]; rows];
+
+        let mut mark = |pos: &Position, c: char| {
+            let x = (pos.x / self.item_size).round() as usize;
+            let y = (pos.y / self.item_size).round() as usize;
+            if let Some(cell) = grid.get_mut(y).and_then(|row| row.get_mut(x)) {
+                *cell = c;
+            }
+        };
+
+        if !self.food.hidden {
+            self.food.cells().iter().for_each(|pos| mark(pos, 'O'));
+        }
+        self.patrols
+            .iter()
+            .for_each(|patrol| mark(&patrol.position, 'X'));
+        self.snake.body.iter().for_each(|pos| mark(pos, '#'));
+
+        grid.into_iter()
+            .map(|row| row.into_iter().collect::<String>() + "\n")
+            .collect()
+    }
+
+    fn reachable_cells(&self, from: Position) -> usize {
+        self.flood(from).order.len()
+    }
+
+    // * Shortest route from the head to the food, head excluded, empty when the food is cut off
+    fn safe_path(&self) -> Vec<Position> {
+        let Some(&head) = self.snake.body.front() else {
+            return Vec::new();
+        };
+        let flood = self.flood(head);
+        // * Cells come out of the search nearest first, so the first food cell is the closest
+        let Some(&target) = flood
+            .order
+            .iter()
+            .find(|&&cell| self.food.covers(&flood.position(cell)))
+        else {
+            return Vec::new();
+        };
+
+        flood.path_to(target)
+    }
+
+    // * Free cells the snake could get to from `from`, flood filling around the body and patrols
+    fn flood(&self, from: Position) -> Flood {
+        let columns = (self.width / self.item_size).floor() as i64;
+        let rows = (self.height / self.item_size).floor() as i64;
+        let cells = (columns * rows) as usize;
+        let mut flood = Flood {
+            columns,
+            rows,
+            item_size: self.item_size,
+            order: Vec::new(),
+            came_from: vec![None; cells],
+            reached: vec![false; cells],
+        };
+
+        let mut blocked = vec![false; cells];
+        self.snake
+            .body
+            .iter()
+            .chain(self.patrols.iter().map(|patrol| &patrol.position))
+            .for_each(|pos| blocked[flood.index(pos)] = true);
+
+        let start = flood.index(&from);
+        let mut seen = vec![false; cells];
+        let mut queue = LinkedList::from([start]);
+        seen[start] = true;
+
+        while let Some(cell) = queue.pop_front() {
+            // * Starting from the head is allowed, but the head itself isn't free
+            if !blocked[cell] {
+                flood.order.push(cell);
+                flood.reached[cell] = true;
+            }
+
+            // * The board wraps, so every cell has four neighbours
+            let (x, y) = (cell as i64 % columns, cell as i64 / columns);
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let next =
+                    ((y + dy).rem_euclid(rows) * columns + (x + dx).rem_euclid(columns)) as usize;
+                if !seen[next] && !blocked[next] {
+                    seen[next] = true;
+                    flood.came_from[next] = Some(cell);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        flood
+    }
+
+    // * Moves food the head has had no way of reaching for too long onto a cell it can reach
+    fn guard_against_starvation(&mut self) -> Result<(), GameError> {
+        let Some(limit) = self.starvation_ticks else {
+            return Ok(());
+        };
+        if self.food.hidden {
+            self.unreachable_ticks = 0;
+            return Ok(());
+        }
+
+        let head = *self.snake.body.front().ok_or(GameError::EmptyBody)?;
+        let flood = self.flood(head);
+        if self.food.cells().iter().any(|pos| flood.reaches(pos)) {
+            self.unreachable_ticks = 0;
+            return Ok(());
+        }
+
+        self.unreachable_ticks += 1;
+        if self.unreachable_ticks > limit && !flood.order.is_empty() {
+            self.move_food_into(&flood.cells());
+            self.food.spawned_at = self.clock.elapsed;
+            self.unreachable_ticks = 0;
+        }
+        Ok(())
+    }
+
+    // * Rolls the food again until the head can get to every cell of it, queued food is placed
+    // * as given and never gets here
+    fn reroll_unreachable_food(&mut self) {
+        let Some(&head) = self.snake.body.front() else {
+            return;
+        };
+        let flood = self.flood(head);
+
+        for _ in 0..FOOD_REROLLS {
+            if self.food.cells().iter().all(|cell| flood.reaches(cell)) {
+                return;
+            }
+            self.reset_food();
+        }
+        // * A board that is mostly walled off could take a long time to hit by chance
+        if !flood.order.is_empty() {
+            self.move_food_into(&flood.cells());
+        }
+    }
+
+    // * Puts a plain single food on a random one of `cells`
+    fn move_food_into(&mut self, cells: &[Position]) {
+        let pick = rand::thread_rng().gen_range(0..cells.len());
+        self.food.position = cells[pick];
+        self.food.pair = None;
+        self.food.block = false;
+    }
+
+    // * Hunger takes a segment every so many ticks without food, true once there is none left to take
+    fn starve(&mut self) -> bool {
+        let Some(hunger) = self.hunger_ticks else {
+            return false;
+        };
+        if self.ticks_since_eating == 0 || !self.ticks_since_eating.is_multiple_of(hunger) {
+            return false;
+        }
+
+        // * Growth still owed goes before the body does
+        if self.snake.pending_growth > 0 {
+            self.snake.pending_growth -= 1;
+        } else if self.snake.body.len() > 1 {
+            self.snake.body.pop_back();
+        } else {
+            return true;
+        }
+        false
+    }
+
+    fn cell_contents(&self, pos: &Position) -> &'static str {
+        if self.snake.body.front() == Some(pos) {
+            "head"
+        } else if self.snake.body.contains(pos) {
+            "snake"
+        } else if self.food.covers(pos) {
+            "food"
+        } else if self.patrols.iter().any(|patrol| patrol.position == *pos) {
+            "patrol"
+        } else {
+            "empty"
+        }
+    }
+
+    fn patrol_collision(&self) -> Result<bool, GameError> {
+        let head = self.snake.body.front().ok_or(GameError::EmptyBody)?;
+
+        Ok(self.patrols.iter().any(|patrol| patrol.position == *head))
+    }
+
+    // * Debug aid, steps the game back to the state before the last tick
+    fn undo(&mut self) {
+        if let Some(snapshot) = self
+            .history
+            .as_mut()
+            .and_then(|history| history.pop_front())
+        {
+            self.restore(snapshot);
+            self.turns.clear();
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            body: self.snake.body.clone(),
+            direction: self.snake.direction,
+            food: self.food.position,
+            food_pair: self.food.pair,
+            food_block: self.food.block,
+            food_hidden: self.food.hidden,
+            food_respawn_in: self.food_respawn_in,
+            food_spawned_at: self.food.spawned_at,
+            pending_growth: self.snake.pending_growth,
+            patrols: self.patrols.clone(),
+            score: self.score,
+            foods_eaten: self.foods_eaten,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.snake.body = snapshot.body;
+        self.snake.direction = snapshot.direction;
+        self.food.position = snapshot.food;
+        self.food.pair = snapshot.food_pair;
+        self.food.block = snapshot.food_block;
+        self.food.hidden = snapshot.food_hidden;
+        self.food_respawn_in = snapshot.food_respawn_in;
+        self.food.spawned_at = snapshot.food_spawned_at;
+        self.snake.pending_growth = snapshot.pending_growth;
+        self.patrols = snapshot.patrols;
+        self.score = snapshot.score;
+        self.foods_eaten = snapshot.foods_eaten;
+        // * The speed follows the food count, so it goes back with it
+        self.speed = self.speed_curve.speed_at(self.foods_eaten);
+    }
+
+    fn handle_input(&mut self, btn: &Button) {
+        if self.splash_left > 0.0 {
+            self.splash_left = 0.0;
+            return;
+        }
+        // * The death replay keeps a history too, but only debug mode lets you rewind it
+        if *btn == Button::Keyboard(Key::U) && self.debug {
+            self.undo();
+            return;
+        }
+        if *btn == Button::Keyboard(Key::B) {
+            print!("{}", self.to_ascii());
+            if let (true, Some(head)) = (self.debug, self.snake.body.front()) {
+                println!("Reachable cells: {}", self.reachable_cells(*head));
+            }
+            return;
+        }
+        // * Practice mode keeps one saved position to jump back to as often as needed
+        if self.practice && *btn == Button::Keyboard(Key::P) {
+            self.scenario = Some(self.snapshot());
+            return;
+        }
+        if self.practice && *btn == Button::Keyboard(Key::Backspace) {
+            if let Some(scenario) = self.scenario.clone() {
+                self.restore(scenario);
+                self.turns.clear();
+            }
+            return;
+        }
+        if *btn == Button::Keyboard(Key::Space) {
+            self.clock.paused = !self.clock.paused;
+            return;
+        }
+
+        // * Turns are checked against the last queued one, so two quick presses can't reverse the snake
+        let last_direction = self
+            .turns
+            .back()
+            .map_or(self.snake.direction, |turn| turn.direction);
+
+        let direction = match *btn {
+            Button::Keyboard(Key::Up | Key::W) => Direction::Up,
+            Button::Keyboard(Key::Down | Key::S) => Direction::Down,
+            Button::Keyboard(Key::Left | Key::A) => Direction::Left,
+            Button::Keyboard(Key::Right | Key::D) => Direction::Right,
+            _ => return,
+        };
+        // * Inversion happens before the checks below, so they still see the real turn
+        let direction = if self.inverted_controls {
+            direction.opposite()
+        } else {
+            direction
+        };
+
+        // * The first key sets off in its direction, from a lone head any direction is fine
+        if self.waiting_for_input {
+            self.snake.direction = direction;
+            self.waiting_for_input = false;
+            return;
+        }
+
+        // * Each player holds one turn, replaced by their latest key until their tick comes up
+        if let Some(steer) = &mut self.co_op {
+            let player = match *btn {
+                Button::Keyboard(Key::Up | Key::Down | Key::Left | Key::Right) => 0,
+                _ => 1,
+            };
+            steer[player] = Some(direction);
+            return;
+        }
+
+        // * Key repeats, or an arrow and its WASD key pressed together, would only
+        // * fill the queue with turns that change nothing
+        if direction == last_direction {
+            return;
+        }
+
+        // * A lone head has no body to run into, so it may turn straight back
+        if direction == last_direction.opposite() && self.snake.body.len() > 1 {
+            return;
+        }
+
+        if self.turns.len() < MAX_QUEUED_TURNS {
+            self.turns.push_back(QueuedTurn {
+                direction,
+                delay: if self.turn_grace { 1 } else { 0 },
+                queued_at: self.ticks,
+            });
+        }
+    }
+}
+
+const UPS: u64 = 10;
+
+#[derive(Clone, Copy)]
+enum Ramp {
+    Linear,
+    Exponential,
+}
+
+// * Ticks per second as the snake eats, starting at `base` and never passing `max`
+#[derive(Clone, Copy)]
+struct SpeedCurve {
+    base: u64,
+    step: f64,
+    max: u64,
+    ramp: Ramp,
+}
+
+impl Default for SpeedCurve {
+    fn default() -> Self {
+        SpeedCurve {
+            base: UPS,
+            step: 0.0,
+            max: UPS * 3,
+            ramp: Ramp::Linear,
+        }
+    }
+}
+
+impl SpeedCurve {
+    fn ramps(&self) -> bool {
+        self.step > 0.0 && self.max > self.base
+    }
+
+    // * Both ramps start out `step` faster per food, the exponential one keeps compounding
+    fn speed_at(&self, foods: u32) -> u64 {
+        let base = self.base as f64;
+        let speed = match self.ramp {
+            Ramp::Linear => base + self.step * foods as f64,
+            Ramp::Exponential => base * (1.0 + self.step / base).powi(foods as i32),
+        };
+        (speed.floor() as u64).clamp(self.base, self.max.max(self.base))
+    }
+}
+
+// * How many cells ahead of an edge the wrap warning starts showing
+const WRAP_WARNING_CELLS: u32 = 2;
+
+// * Size of one font pixel on screen
+const TEXT_PIXEL: f64 = 2.0;
+
+const PAUSE_DIM_ALPHA: f32 = 0.5;
+const PAUSE_DIM_FADE_SECONDS: f64 = 0.2;
+const RESPAWN_FLASH_SECONDS: f64 = 0.3;
+
+// * Points awarded per food, the bonuses scale with the snake length and the tick speed
+#[derive(Clone, Copy)]
+struct Scoring {
+    mode: ScoreMode,
+    base: u32,
+    length_bonus: u32,
+    speed_bonus: u32,
+    // * Extra points for reaching food quickly, halved every `quick_half_life` ticks
+    quick_bonus: u32,
+    quick_half_life: u32,
+    // * Added every time the snake wraps over an edge, negative for a penalty
+    wrap_points: i32,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Scoring {
+            mode: ScoreMode::Formula,
+            base: 1,
+            length_bonus: 0,
+            speed_bonus: 0,
+            quick_bonus: 0,
+            quick_half_life: 10,
+            wrap_points: 0,
+        }
+    }
+}
+
+impl Scoring {
+    fn quick_award(&self, ticks_since_food: u64) -> u32 {
+        let decay = 0.5_f64.powf(ticks_since_food as f64 / self.quick_half_life as f64);
+        (self.quick_bonus as f64 * decay).round() as u32
+    }
+}
+
+// * Everything drawn in a frame, `ALL` is the default order from bottom to top
+#[derive(Clone, Copy, PartialEq)]
+enum Layer {
+    Background,
+    Checkerboard,
+    Image,
+    Trail,
+    NextCell,
+    SafePath,
+    Snake,
+    Food,
+    Patrols,
+    Particles,
+    Torus,
+    Fog,
+    Warnings,
+    Flash,
+    PauseDim,
+    Text,
+    Tooltip,
+    EndScreen,
+    Splash,
+}
+
+impl Layer {
+    const ALL: [Layer; 19] = [
+        Layer::Background,
+        Layer::Checkerboard,
+        Layer::Image,
+        Layer::Trail,
+        Layer::NextCell,
+        Layer::SafePath,
+        Layer::Snake,
+        Layer::Food,
+        Layer::Patrols,
+        Layer::Particles,
+        Layer::Torus,
+        Layer::Fog,
+        Layer::Warnings,
+        Layer::Flash,
+        Layer::PauseDim,
+        Layer::Text,
+        Layer::Tooltip,
+        Layer::EndScreen,
+        Layer::Splash,
+    ];
+    const NAMES: [&'static str; 19] = [
+        "background",
+        "checkerboard",
+        "image",
+        "trail",
+        "next-cell",
+        "safe-path",
+        "snake",
+        "food",
+        "patrols",
+        "particles",
+        "torus",
+        "fog",
+        "warnings",
+        "flash",
+        "pause-dim",
+        "text",
+        "tooltip",
+        "end-screen",
+        "splash",
+    ];
+
+    fn from_name(name: &str) -> Option<Layer> {
+        Layer::NAMES
+            .iter()
+            .position(|&known| known == name)
+            .map(|i| Layer::ALL[i])
+    }
+}
+
+const AWARD_SECONDS: f64 = 0.8;
+
+// * Points a food was worth, floated up from where it was eaten
+struct Award {
+    points: u32,
+    position: Position,
+    shown_at: f64,
+}
+
+// * What the shown score counts, only the formula uses the points from `Scoring`
+#[derive(Clone, Copy, PartialEq)]
+enum ScoreMode {
+    FoodCount,
+    Length,
+    Formula,
+}
+
+impl ScoreMode {
+    fn from_name(name: &str) -> Option<ScoreMode> {
+        match name {
+            "food" => Some(ScoreMode::FoodCount),
+            "length" => Some(ScoreMode::Length),
+            "formula" => Some(ScoreMode::Formula),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Score {
+    food: u32,
+    length: u32,
+    speed: u32,
+    quick: u32,
+    objective: u32,
+    wrap: i32,
+}
+
+impl Score {
+    // * Wrap penalties can't take the score below zero
+    fn total(&self) -> u32 {
+        let earned = self.food + self.length + self.speed + self.quick + self.objective;
+        (earned as i64 + self.wrap as i64).max(0) as u32
+    }
+}
+
+// * Optional challenge picked at the start of a game, completing it is worth a bonus
+#[derive(Clone, Copy)]
+enum Objective {
+    ReachLength(usize),
+    Survive(f64),
+    EatWithoutWrapping(u32),
+}
+
+const OBJECTIVE_BONUS: u32 = 10;
+// * The challenge banner stays up a little longer than a milestone so there's time to read it
+const OBJECTIVE_INTRO_SECONDS: f64 = 2.0;
+
+impl Objective {
+    fn random() -> Self {
+        let mut rng = rand::thread_rng();
+        match rng.gen_range(0..3) {
+            0 => Objective::ReachLength(rng.gen_range(2..=6) * 5),
+            1 => Objective::Survive(rng.gen_range(3..=9) as f64 * 10.0),
+            _ => Objective::EatWithoutWrapping(rng.gen_range(3..=8)),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Objective::ReachLength(length) => format!("Reach length {}", length),
+            Objective::Survive(seconds) => format!("Survive {}s", seconds),
+            Objective::EatWithoutWrapping(foods) => format!("Eat {} without wrapping", foods),
+        }
+    }
+}
+
+// * Source of time for anything animated, it stands still while the game is paused
+struct GameClock {
+    elapsed: f64,
+    paused: bool,
+    // * Seconds left of a short automatic pause, counted in real time
+    hold: f64,
+    // * Real time spent in the current pause
+    paused_for: f64,
+}
+
+impl GameClock {
+    fn new() -> Self {
+        GameClock {
+            elapsed: 0.0,
+            paused: false,
+            hold: 0.0,
+            paused_for: 0.0,
+        }
+    }
+
+    fn advance(&mut self, dt: f64) {
+        if self.paused {
+            self.paused_for += dt;
+            return;
+        }
+        self.paused_for = 0.0;
+        if self.hold > 0.0 {
+            self.hold = (self.hold - dt).max(0.0);
+            return;
+        }
+        self.elapsed += dt;
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Milestone {
+    length: usize,
+    // * Major milestones also hold the game still for a moment
+    major: bool,
+}
+
+// * Result of a breadth first search over the free cells, kept by cell index so lookups and
+// * walking a route back are cheap
+struct Flood {
+    columns: i64,
+    rows: i64,
+    item_size: f64,
+    // * Free cells in the order they were reached, nearest first
+    order: Vec<usize>,
+    // * Cell each cell was first reached from, None for the start and cells never reached
+    came_from: Vec<Option<usize>>,
+    reached: Vec<bool>,
+}
+
+impl Flood {
+    fn index(&self, pos: &Position) -> usize {
+        let x = (pos.x / self.item_size).round() as i64;
+        let y = (pos.y / self.item_size).round() as i64;
+        (y.rem_euclid(self.rows) * self.columns + x.rem_euclid(self.columns)) as usize
+    }
+
+    fn position(&self, index: usize) -> Position {
+        let index = index as i64;
+        cell_pos(index % self.columns, index / self.columns, self.item_size)
+    }
+
+    fn reaches(&self, pos: &Position) -> bool {
+        self.reached[self.index(pos)]
+    }
+
+    fn cells(&self) -> Vec<Position> {
+        self.order.iter().map(|&cell| self.position(cell)).collect()
+    }
+
+    // * Route from the start to `target`, start excluded
+    fn path_to(&self, target: usize) -> Vec<Position> {
+        let mut path = Vec::new();
+        let mut cell = target;
+        while let Some(previous) = self.came_from[cell] {
+            path.push(self.position(cell));
+            cell = previous;
+        }
+        path.reverse();
+        path
+    }
+}
+
+const FOOD_REROLLS: usize = 20;
+
+// * Only the start of the route is shown, the player still has to find the rest
+const SAFE_PATH_CELLS: usize = 6;
+
+const SPLASH_TITLE: &str = "SNAKE";
+
+const MILESTONE_BANNER_SECONDS: f64 = 1.0;
+const MILESTONE_HOLD_SECONDS: f64 = 0.75;
+
+// * Summary of a finished game for the end screen
+struct GameStats {
+    won: bool,
+    score: u32,
+    length: usize,
+    seconds: f64,
+    foods: u32,
+    wraps: u32,
+    cause: Option<DeathCause>,
+}
+
+impl GameStats {
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            if self.won { "You win!" } else { "Game over!" }.to_string(),
+            format!("Score: {}", self.score),
+            format!("Length: {}", self.length),
+            format!("Time: {}s", self.seconds.floor()),
+            format!("Food eaten: {}", self.foods),
+            format!("Wraps: {}", self.wraps),
+        ];
+        if let (false, Some(cause)) = (self.won, self.cause) {
+            lines.push(cause.to_string());
+        }
+        lines
+    }
+}
+
+struct Banner {
+    text: String,
+    shown_at: f64,
+    seconds: f64,
+}
+
+const MAX_QUEUED_TURNS: usize = 3;
+
+struct QueuedTurn {
+    direction: Direction,
+    // * Ticks left before the turn is applied
+    delay: u32,
+    queued_at: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+enum Direction {
+    Left,
+    #[default]
+    Right,
+    Up,
+    Down,
+}
+
+impl Direction {
+    fn from_name(name: &str) -> Option<Direction> {
+        match name {
+            "left" => Some(Direction::Left),
+            "right" => Some(Direction::Right),
+            "up" => Some(Direction::Up),
+            "down" => Some(Direction::Down),
+            _ => None,
+        }
+    }
+
+    fn opposite(self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Position {
+    x: f64,
+    y: f64,
+}
+
+// * Ticks of history kept for undo and the death replay unless --history says otherwise
+const HISTORY_LENGTH: usize = 10;
+
+#[derive(Clone)]
+struct Snapshot {
+    body: LinkedList<Position>,
+    direction: Direction,
+    food: Position,
+    food_pair: Option<Position>,
+    food_block: bool,
+    food_hidden: bool,
+    food_respawn_in: u64,
+    food_spawned_at: f64,
+    pending_growth: usize,
+    patrols: Vec<Patrol>,
+    score: Score,
+    foods_eaten: u32,
+}
+
+const REPLAY_FRAME_SECONDS: f64 = 0.3;
+
+const BURST_PARTICLES_PER_SEGMENT: usize = 4;
+const BURST_PARTICLE_SIZE: f64 = 3.0;
+// * Pixels per second, each particle gets a random speed in this range
+const BURST_SPEEDS: std::ops::Range<f64> = 30.0..90.0;
+
+// * The snake flying apart on its last death, the game ends once it has faded out
+struct Burst {
+    // * Start point and velocity of every particle
+    particles: Vec<([f64; 2], [f64; 2])>,
+    color: [f32; 4],
+    started_at: f64,
+    seconds: f64,
+}
+
+impl Burst {
+    fn new(snake: &Snake, started_at: f64, seconds: f64) -> Self {
+        let mut rng = rand::thread_rng();
+        let half = snake.size / 2.0;
+        let particles = snake
+            .body
+            .iter()
+            .flat_map(|pos| std::iter::repeat_n(*pos, BURST_PARTICLES_PER_SEGMENT))
+            .map(|pos| {
+                let angle = rng.gen_range(0.0..f64::consts::TAU);
+                let speed = rng.gen_range(BURST_SPEEDS);
+                (
+                    [pos.x + half, pos.y + half],
+                    [angle.cos() * speed, angle.sin() * speed],
+                )
+            })
+            .collect();
+
+        Burst {
+            particles,
+            color: snake.body_color(),
+            started_at,
+            seconds,
+        }
+    }
+
+    fn render(&self, gl: &mut GlGraphics, viewport: Viewport, now: f64) {
+        let t = (now - self.started_at).max(0.0);
+        let [r, g, b, _] = self.color;
+        let color = [r, g, b, (1.0 - t / self.seconds).max(0.0) as f32];
+
+        gl.draw(viewport, |c, gl| {
+            for ([x, y], [vx, vy]) in &self.particles {
+                let square = graphics::rectangle::centered_square(
+                    x + vx * t,
+                    y + vy * t,
+                    BURST_PARTICLE_SIZE / 2.0,
+                );
+                graphics::rectangle(color, square, c.transform, gl);
+            }
+        });
+    }
+}
+
+// * Slow playback of the ticks leading up to a death
+struct Replay {
+    frames: LinkedList<Snapshot>,
+    fatal: Position,
+    next_frame_at: f64,
+}
+
+enum SnakeMoveResult {
+    Ok,
+    // * Holds the food cell that was eaten
+    Food(Position),
+    // * Holds how many segments were bitten off
+    Bitten(usize),
+    // * Holds the cell the snake died on and what killed it
+    End(Position, DeathCause),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DeathCause {
+    SelfCollision,
+    // * Wrapping over an edge straight into the body, easy to miss coming
+    WrapIntoSelf,
+    Patrol,
+    Starved,
+}
+
+impl fmt::Display for DeathCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeathCause::SelfCollision => write!(f, "Ran into your own body"),
+            DeathCause::WrapIntoSelf => write!(f, "Wrapped around into your own body"),
+            DeathCause::Patrol => write!(f, "Hit a patrol"),
+            DeathCause::Starved => write!(f, "Starved"),
+        }
+    }
+}
+
+struct Snake {
+    body: LinkedList<Position>,
+    size: f64,
+    color: [f32; 4],
+    inset: f64,
+    // * Rounds off the segments, 0 keeps the cheaper plain squares
+    corner_radius: f64,
+    // * Fills the gaps around turns so the snake reads as one piece there
+    smooth_corners: bool,
+    // * Shifts the colour as the snake grows instead of using the theme's
+    length_colors: bool,
+    // * Two dots on the head looking the way it's going, in the background colour
+    eyes: bool,
+    eye_color: [f32; 4],
+    max_length: Option<usize>,
+    // * Running into the body bites off the rest of the snake instead of ending the game
+    shrink_on_bite: bool,
+    // * Experimental, the tail end can eat too and the snake then grows at the head
+    tail_eats: bool,
+    // * Moves left on which the tail stays put
+    pending_growth: usize,
+    // * Times the head has wrapped over an edge
+    wraps: u32,
+    direction: Direction,
+}
+
+impl Snake {
+    fn new(size: f64, initial_pos: Position, theme: &Theme) -> Self {
+        Snake {
+            body: LinkedList::from([initial_pos]),
+            size,
+            color: theme.snake,
+            inset: theme.cell_inset,
+            corner_radius: 0.0,
+            smooth_corners: false,
+            length_colors: false,
+            eyes: true,
+            eye_color: theme.background,
+            max_length: None,
+            shrink_on_bite: false,
+            tail_eats: false,
+            pending_growth: 0,
+            wraps: 0,
+            direction: Direction::Right,
+        }
+    }
+
+    fn body_color(&self) -> [f32; 4] {
+        if self.length_colors {
+            length_color(self.body.len())
+        } else {
+            self.color
+        }
+    }
+
+    fn render(&self, gl: &mut GlGraphics, viewport: Viewport) {
+        let squares: Vec<graphics::types::Rectangle> = self
+            .body
+            .iter()
+            .map(|pos| cell_square(pos, self.size, self.inset))
+            .collect();
+
+        let mut bridges = Vec::new();
+        if self.smooth_corners {
+            let cells: Vec<&Position> = self.body.iter().collect();
+            for window in cells.windows(3) {
+                let (prev, cur, next) = (window[0], window[1], window[2]);
+                if is_corner(prev, cur, next) {
+                    bridges.extend(
+                        [prev, next]
+                            .into_iter()
+                            .filter_map(|other| self.bridge(cur, other)),
+                    );
+                }
+            }
+        }
+
+        let color = self.body_color();
+        let eyes = self
+            .body
+            .front()
+            .filter(|_| self.eyes)
+            .map(|head| eye_squares(head, self.direction, self.size));
+
+        gl.draw(viewport, |c, gl| {
+            let transform = c.transform;
+
+            bridges
+                .into_iter()
+                .for_each(|bridge| graphics::rectangle(color, bridge, transform, gl));
+
+            if self.corner_radius > 0.0 {
+                let segment = graphics::Rectangle::new_round(color, self.corner_radius);
+                squares
+                    .into_iter()
+                    .for_each(|square| segment.draw(square, &c.draw_state, transform, gl))
+            } else {
+                squares
+                    .into_iter()
+                    .for_each(|square| graphics::rectangle(color, square, transform, gl))
+            }
+
+            if let Some(eyes) = eyes {
+                eyes.into_iter()
+                    .for_each(|eye| graphics::rectangle(self.eye_color, eye, transform, gl));
+            }
+        });
+    }
+    fn update(
+        &mut self,
+        food: &Food,
+        width: f64,
+        height: f64,
+    ) -> Result<SnakeMoveResult, GameError> {
+        let head = self.body.front().ok_or(GameError::EmptyBody)?;
+        let mut new_x = head.x;
+        let mut new_y = head.y;
+        match self.direction {
+            Direction::Left => new_x -= self.size,
+            Direction::Right => new_x += self.size,
+            Direction::Up => new_y -= self.size,
+            Direction::Down => new_y += self.size,
+        }
+
+        let mut new_pos = Position { x: new_x, y: new_y };
+
+        if new_pos.x >= width {
+            new_pos.x = 0.0;
+        } else if new_pos.x < 0.0 {
+            new_pos.x = width - self.size;
+        }
+
+        if new_pos.y >= height {
+            new_pos.y = 0.0;
+        } else if new_pos.y < 0.0 {
+            new_pos.y = height - self.size;
+        }
+
+        let wrapped = new_pos.x != new_x || new_pos.y != new_y;
+        if self.self_collision(&new_pos) {
+            let bitten = self.body.iter().position(|pos| *pos == new_pos);
+            let Some(bitten) = bitten.filter(|_| self.shrink_on_bite) else {
+                let cause = if wrapped {
+                    DeathCause::WrapIntoSelf
+                } else {
+                    DeathCause::SelfCollision
+                };
+                return Ok(SnakeMoveResult::End(new_pos, cause));
+            };
+
+            // * Everything from the bitten segment back falls off, then the head moves in
+            self.wraps += wrapped as u32;
+            let lost = self.body.split_off(bitten).len();
+            self.body.push_front(new_pos);
+            self.body.pop_back().ok_or(GameError::EmptyBody)?;
+            Ok(SnakeMoveResult::Bitten(lost))
+        } else {
+            self.wraps += wrapped as u32;
+            self.body.push_front(new_pos);
+
+            if food.covers(&new_pos) {
+                // * At the length cap food still scores but the tail keeps moving
+                if self.max_length.is_some_and(|max| self.body.len() > max) {
+                    self.body.pop_back().ok_or(GameError::EmptyBody)?;
+                }
+                Ok(SnakeMoveResult::Food(new_pos))
+            } else {
+                let at_cap = self.max_length.is_some_and(|max| self.body.len() > max);
+                if self.pending_growth > 0 && !at_cap {
+                    self.pending_growth -= 1;
+                } else {
+                    self.body.pop_back().ok_or(GameError::EmptyBody)?;
+                }
+
+                let tail = *self.body.back().ok_or(GameError::EmptyBody)?;
+                // * Only queued food can be under the body, random food is never placed there
+                if self.tail_eats && food.covers(&tail) {
+                    self.pending_growth += 1;
+                    Ok(SnakeMoveResult::Food(tail))
+                } else {
+                    Ok(SnakeMoveResult::Ok)
+                }
+            }
+        }
+    }
+
+    // * Strip joining the centres of two neighbouring segments, none across a wrap
+    fn bridge(&self, a: &Position, b: &Position) -> Option<graphics::types::Rectangle> {
+        let (dx, dy) = ((a.x - b.x).abs(), (a.y - b.y).abs());
+        if dx + dy != self.size {
+            return None;
+        }
+
+        let width = self.size - self.inset * 2.0;
+        let (x, w) = if dx == 0.0 {
+            (a.x + self.inset, width)
+        } else {
+            (a.x.min(b.x) + self.size / 2.0, dx)
+        };
+        let (y, h) = if dy == 0.0 {
+            (a.y + self.inset, width)
+        } else {
+            (a.y.min(b.y) + self.size / 2.0, dy)
+        };
+        Some([x, y, w, h])
+    }
+
+    fn self_collision(&self, new_pos: &Position) -> bool {
+        self.body
+            .iter()
+            .any(|pos| pos.x == new_pos.x && pos.y == new_pos.y)
+    }
+}
+
+struct Food {
+    position: Position,
+    // * Second cell of a double food
+    pair: Option<Position>,
+    double: bool,
+    size: f64,
+    color: [f32; 4],
+    inset: f64,
+    queue: LinkedList<Position>,
+    cycle_queue: bool,
+    // * Clock time the food appeared, it grows in from nothing when `pop_in` is on
+    spawned_at: f64,
+    pop_in: bool,
+    // * Off the board while waiting to respawn
+    hidden: bool,
+    big: bool,
+    // * Whether this food is big, covering a 2x2 block down and right of `position`
+    block: bool,
+}
+
+impl Food {
+    fn new(initial_pos: Position, item_size: f64, theme: &Theme) -> Self {
+        Food {
+            position: initial_pos,
+            pair: None,
+            double: false,
+            size: item_size,
+            color: theme.food,
+            inset: theme.cell_inset,
+            queue: LinkedList::new(),
+            cycle_queue: false,
+            spawned_at: 0.0,
+            pop_in: false,
+            hidden: false,
+            big: false,
+            block: false,
+        }
+    }
+    fn render(&self, gl: &mut GlGraphics, viewport: Viewport, now: f64) {
+        if self.hidden {
+            return;
+        }
+
+        let scale = if self.pop_in {
+            spawn_scale(now - self.spawned_at)
+        } else {
+            1.0
+        };
+        // * Big food is drawn as one square over its whole block
+        let size = if self.block {
+            self.size * 2.0
+        } else {
+            self.size
+        };
+        // * Shrinking is done by growing the inset, which keeps the food centred in its cell
+        let inset = self.inset + (size / 2.0 - self.inset) * (1.0 - scale);
+
+        let squares: Vec<graphics::types::Rectangle> = if self.block {
+            vec![cell_square(&self.position, size, inset)]
+        } else {
+            [Some(self.position), self.pair]
+                .iter()
+                .flatten()
+                .map(|pos| cell_square(pos, size, inset))
+                .collect()
+        };
+
+        gl.draw(viewport, |c, gl| {
+            let transform = c.transform;
+
+            squares
+                .into_iter()
+                .for_each(|square| graphics::rectangle(self.color, square, transform, gl))
+        });
+    }
+
+    fn covers(&self, pos: &Position) -> bool {
+        !self.hidden && self.cells().contains(pos)
+    }
+
+    fn cells(&self) -> Vec<Position> {
+        if self.block {
+            return block_cells(&self.position, self.size).to_vec();
+        }
+        [Some(self.position), self.pair]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    // * Eats the cell at `pos`, returns true once no cell of the food is left
+    fn eat(&mut self, pos: &Position) -> bool {
+        match self.pair.take() {
+            Some(pair) => {
+                if self.position == *pos {
+                    self.position = pair;
+                }
+                false
+            }
+            None => true,
+        }
+    }
+
+    fn reset(
+        &mut self,
+        width: f64,
+        height: f64,
+        item_size: f64,
+        snake: &Snake,
+        patrols: &[Position],
+    ) {
+        self.block = false;
+        if self.next_queued() {
+            return;
+        }
+
+        let taken = |pos: &Position| snake.self_collision(pos) || patrols.contains(pos);
+
+        // * Big food picks from every free 2x2 block, only a board without one gets a single food
+        if self.big && rand::thread_rng().gen_ratio(1, BIG_FOOD_ODDS) {
+            let blocks = free_blocks(width, height, item_size, taken);
+            if !blocks.is_empty() {
+                self.position = blocks[rand::thread_rng().gen_range(0..blocks.len())];
+                self.block = true;
+                return;
+            }
+        }
+
+        let mut new_pos = random_pos(width, height, item_size);
+        while taken(&new_pos) {
+            new_pos = random_pos(width, height, item_size);
+        }
+        self.position = new_pos;
+
+        if self.double && rand::thread_rng().gen_ratio(1, DOUBLE_FOOD_ODDS) {
+            self.pair = self.free_neighbour(width, height, taken);
+        }
+    }
+
+    fn free_neighbour(
+        &self,
+        width: f64,
+        height: f64,
+        taken: impl Fn(&Position) -> bool,
+    ) -> Option<Position> {
+        let Position { x, y } = self.position;
+
+        [
+            Position {
+                x: x - self.size,
+                y,
+            },
+            Position {
+                x: x + self.size,
+                y,
+            },
+            Position {
+                x,
+                y: y - self.size,
+            },
+            Position {
+                x,
+                y: y + self.size,
+            },
+        ]
+        .into_iter()
+        .filter(|pos| pos.x >= 0.0 && pos.x < width && pos.y >= 0.0 && pos.y < height)
+        .find(|pos| !taken(pos))
+    }
+
+    // * Moves the food to the next queued position, returns false once the queue runs out
+    fn next_queued(&mut self) -> bool {
+        match self.queue.pop_front() {
+            Some(pos) => {
+                if self.cycle_queue {
+                    self.queue.push_back(pos);
+                }
+                self.position = pos;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// * One in this many foods spawns as a double when double food is on
+const DOUBLE_FOOD_ODDS: u32 = 4;
+
+// * One in this many foods spawns big when big food is on, worth this many times the points
+const BIG_FOOD_ODDS: u32 = 5;
+const BIG_FOOD_MULTIPLIER: u32 = 4;
+
+// * Top left cells of every 2x2 block that fits on the board with none of its cells taken
+fn free_blocks(
+    width: f64,
+    height: f64,
+    size: f64,
+    taken: impl Fn(&Position) -> bool,
+) -> Vec<Position> {
+    let columns = (width / size).floor() as i64;
+    let rows = (height / size).floor() as i64;
+
+    (0..columns - 1)
+        .flat_map(|x| (0..rows - 1).map(move |y| cell_pos(x, y, size)))
+        .filter(|pos| block_cells(pos, size).iter().all(|cell| !taken(cell)))
+        .collect()
+}
+
+fn block_cells(top_left: &Position, size: f64) -> [Position; 4] {
+    let Position { x, y } = *top_left;
+    [
+        Position { x, y },
+        Position { x: x + size, y },
+        Position { x, y: y + size },
+        Position {
+            x: x + size,
+            y: y + size,
+        },
+    ]
+}
+
+const FOOD_POP_SECONDS: f64 = 0.2;
+
+// * Size of newly spawned food relative to its cell, easing out to full size
+fn spawn_scale(age: f64) -> f64 {
+    let t = (age / FOOD_POP_SECONDS).clamp(0.0, 1.0);
+    1.0 - (1.0 - t) * (1.0 - t)
+}
+
+// * Obstacle walking back and forth between two cells on the same row or column
+#[derive(Clone)]
+struct Patrol {
+    position: Position,
+    direction: Direction,
+    from: Position,
+    to: Position,
+    size: f64,
+    color: [f32; 4],
+    inset: f64,
+}
+
+impl Patrol {
+    fn new(from: Position, to: Position, item_size: f64, theme: &Theme) -> Self {
+        let direction = if to.x < from.x {
+            Direction::Left
+        } else if to.x > from.x {
+            Direction::Right
+        } else if to.y < from.y {
+            Direction::Up
+        } else {
+            Direction::Down
+        };
+
+        Patrol {
+            position: from,
+            direction,
+            from,
+            to,
+            size: item_size,
+            color: theme.hazard,
+            inset: theme.cell_inset,
+        }
+    }
+
+    fn render(&self, gl: &mut GlGraphics, viewport: Viewport) {
+        let square = cell_square(&self.position, self.size, self.inset);
+
+        gl.draw(viewport, |c, gl| {
+            let transform = c.transform;
+            graphics::rectangle(self.color, square, transform, gl)
+        });
+    }
+
+    fn update(&mut self) {
+        if self.position == self.to {
+            std::mem::swap(&mut self.from, &mut self.to);
+            self.direction = self.direction.opposite();
+        }
+        if self.position == self.to {
+            return;
+        }
+
+        match self.direction {
+            Direction::Left => self.position.x -= self.size,
+            Direction::Right => self.position.x += self.size,
+            Direction::Up => self.position.y -= self.size,
+            Direction::Down => self.position.y += self.size,
+        }
+    }
+}
+
+const TRAIL_LENGTH: usize = 6;
+const TRAIL_FADE_TICKS: u32 = 4;
+
+struct TrailCell {
+    position: Position,
+    ticks_left: u32,
+}
+
+// * Fading shadow left behind on the cells the tail recently vacated
+struct Trail {
+    cells: LinkedList<TrailCell>,
+    size: f64,
+    color: [f32; 4],
+    inset: f64,
+}
+
+impl Trail {
+    fn new(item_size: f64, theme: &Theme) -> Self {
+        let [r, g, b, _] = theme.snake;
+
+        Trail {
+            cells: LinkedList::new(),
+            size: item_size,
+            color: [r, g, b, 0.35],
+            inset: theme.cell_inset,
+        }
+    }
+
+    fn render(&self, gl: &mut GlGraphics, viewport: Viewport) {
+        let squares: Vec<([f32; 4], graphics::types::Rectangle)> = self
+            .cells
+            .iter()
+            .map(|cell| {
+                let fade = cell.ticks_left as f32 / TRAIL_FADE_TICKS as f32;
+                let mut color = self.color;
+                color[3] *= fade;
+
+                let square = cell_square(&cell.position, self.size, self.inset);
+                (color, square)
+            })
+            .collect();
+
+        gl.draw(viewport, |c, gl| {
+            let transform = c.transform;
+
+            squares
+                .into_iter()
+                .for_each(|(color, square)| graphics::rectangle(color, square, transform, gl))
+        });
+    }
+
+    fn update(&mut self, vacated: Option<Position>) {
+        self.cells.iter_mut().for_each(|cell| cell.ticks_left -= 1);
+
+        // * Cells are pushed in order, so the expired ones are always at the back
+        while self.cells.back().is_some_and(|cell| cell.ticks_left == 0) {
+            self.cells.pop_back();
+        }
+
+        if let Some(position) = vacated {
+            self.cells.push_front(TrailCell {
+                position,
+                ticks_left: TRAIL_FADE_TICKS,
+            });
+        }
+
+        while self.cells.len() > TRAIL_LENGTH {
+            self.cells.pop_back();
+        }
+    }
+}
+
+#[derive(Debug)]
+enum GameError {
+    Window(String),
+    Config(String),
+    Asset(String),
+    EmptyBody,
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::Window(err) => write!(f, "Could not create the window: {}", err),
+            GameError::Config(err) => write!(f, "Invalid settings: {}", err),
+            GameError::Asset(err) => write!(f, "Asset problem: {}", err),
+            GameError::EmptyBody => write!(f, "Snake has no body"),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+#[derive(Clone, Copy)]
+struct Theme {
+    background: [f32; 4],
+    snake: [f32; 4],
+    food: [f32; 4],
+    hazard: [f32; 4],
+    text: [f32; 4],
+    // * Pixels trimmed from each side of a cell so neighbouring cells show a gap
+    cell_inset: f64,
+}
+
+impl Theme {
+    const CLASSIC: Theme = Theme {
+        background: [1.0, 1.0, 1.0, 1.0],
+        snake: [1.0, 0.0, 0.0, 1.0],
+        food: [0.0, 1.0, 0.0, 1.0],
+        hazard: [1.0, 0.55, 0.0, 1.0],
+        text: [0.2, 0.2, 0.2, 1.0],
+        cell_inset: 0.0,
+    };
+
+    // * Dark pixels on the greenish LCD of the old phones
+    const NOKIA: Theme = Theme {
+        background: [0.780, 0.941, 0.847, 1.0],
+        snake: [0.263, 0.322, 0.239, 1.0],
+        food: [0.263, 0.322, 0.239, 1.0],
+        hazard: [0.263, 0.322, 0.239, 1.0],
+        text: [0.263, 0.322, 0.239, 1.0],
+        cell_inset: 1.0,
+    };
+
+    fn from_name(name: &str) -> Option<Theme> {
+        match name {
+            "classic" => Some(Theme::CLASSIC),
+            "nokia" => Some(Theme::NOKIA),
+            _ => None,
+        }
+    }
+
+    // * Every other cell is a touch darker than the background
+    fn checker_shade(&self, x: i64, y: i64) -> [f32; 4] {
+        let [r, g, b, a] = self.background;
+        if (x + y) % 2 == 0 {
+            [r, g, b, a]
+        } else {
+            [r * 0.93, g * 0.93, b * 0.93, a]
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::CLASSIC
+    }
+}
+
+#[derive(Default)]
+struct Settings {
+    trail: bool,
+    reduced_motion: bool,
+    food_queue: Vec<(i64, i64)>,
+    cycle_food: bool,
+    debug: bool,
+    theme: Theme,
+    corner_radius: f64,
+    smooth_corners: bool,
+    length_colors: bool,
+    patrols: Vec<((i64, i64), (i64, i64))>,
+    turn_grace: bool,
+    coyote_time: bool,
+    dump_board: bool,
+    max_length: Option<usize>,
+    shrink_on_bite: bool,
+    tail_eats: bool,
+    goal_foods: Option<u32>,
+    initial_direction: Direction,
+    zen: bool,
+    cell_padding: Option<f64>,
+    scoring: Scoring,
+    wrap_warning: bool,
+    death_replay: bool,
+    background: Option<String>,
+    max_ticks: Option<u64>,
+    double_food: bool,
+    flip_y: bool,
+    margin: f64,
+    checkerboard: bool,
+    fog_radius: Option<f64>,
+    show_next_cell: bool,
+    milestones: Vec<Milestone>,
+    check: bool,
+    kiosk_delay: Option<f64>,
+    msaa: u8,
+    lives: Option<u32>,
+    max_fps: Option<u64>,
+    objective: bool,
+    speed_curve: SpeedCurve,
+    food_delay: u64,
+    start_on_input: bool,
+    inverted_controls: bool,
+    big_food: bool,
+    show_speed: bool,
+    end_screen: bool,
+    push_food: bool,
+    practice: bool,
+    starvation_ticks: Option<u64>,
+    layers: Option<Vec<Layer>>,
+    hunger_ticks: Option<u64>,
+    integer_scale: bool,
+    co_op: bool,
+    torus_preview: bool,
+    splash_seconds: f64,
+    no_eyes: bool,
+    assist_path: bool,
+    pause_dim: bool,
+    burst_seconds: Option<f64>,
+    reachable_food: bool,
+    history_length: Option<usize>,
+}
+
+impl Settings {
+    fn from_args() -> Result<Self, GameError> {
+        Settings::parse(std::env::args().skip(1))
+    }
+
+    // * Parses everything after the program name, tests hand their own arguments in here
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, GameError> {
+        let mut settings = Settings::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--trail" => settings.trail = true,
+                "--reduced-motion" => settings.reduced_motion = true,
+                "--food" => {
+                    let value = args.next().ok_or_else(|| {
+                        GameError::Config("--food needs a cell, e.g. --food 3,4".to_string())
+                    })?;
+                    settings.food_queue.push(parse_cell(&value)?);
+                }
+                "--cycle-food" => settings.cycle_food = true,
+                "--double-food" => settings.double_food = true,
+                "--big-food" => settings.big_food = true,
+                "--push-food" => settings.push_food = true,
+                "--flip-y" => settings.flip_y = true,
+                "--checkerboard" => settings.checkerboard = true,
+                "--history" => {
+                    let length = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|&length| length > 0)
+                        .ok_or_else(|| {
+                            GameError::Config("--history needs a number of ticks".to_string())
+                        })?;
+                    settings.history_length = Some(length);
+                }
+                "--death-burst" => {
+                    let seconds = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|seconds: &f64| *seconds > 0.0)
+                        .ok_or_else(|| {
+                            GameError::Config("--death-burst needs a number of seconds".to_string())
+                        })?;
+                    settings.burst_seconds = Some(seconds);
+                }
+                "--pause-dim" => settings.pause_dim = true,
+                "--assist-path" => settings.assist_path = true,
+                "--show-next-cell" => settings.show_next_cell = true,
+                "--show-speed" => settings.show_speed = true,
+                "--debug" => settings.debug = true,
+                "--practice" => settings.practice = true,
+                "--corner-radius" => {
+                    settings.corner_radius = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|radius: &f64| *radius >= 0.0)
+                        .ok_or_else(|| {
+                            GameError::Config(
+                                "--corner-radius needs a non-negative number of pixels".to_string(),
+                            )
+                        })?;
+                }
+                "--smooth-corners" => settings.smooth_corners = true,
+                "--length-colors" => settings.length_colors = true,
+                "--no-eyes" => settings.no_eyes = true,
+                "--patrol" => {
+                    let usage = || {
+                        GameError::Config(
+                            "--patrol needs two cells, e.g. --patrol 2,5 9,5".to_string(),
+                        )
+                    };
+                    let from = parse_cell(&args.next().ok_or_else(usage)?)?;
+                    let to = parse_cell(&args.next().ok_or_else(usage)?)?;
+                    settings.patrols.push((from, to));
+                }
+                "--turn-grace" => settings.turn_grace = true,
+                "--co-op" => settings.co_op = true,
+                "--inverted-controls" => settings.inverted_controls = true,
+                "--coyote-time" => settings.coyote_time = true,
+                "--zen" => settings.zen = true,
+                "--start-on-input" => settings.start_on_input = true,
+                "--challenge" => settings.objective = true,
+                "--shrink-on-bite" => settings.shrink_on_bite = true,
+                "--tail-eats" => settings.tail_eats = true,
+                "--wrap-warning" => settings.wrap_warning = true,
+                "--death-replay" => settings.death_replay = true,
+                "--dump-board" => settings.dump_board = true,
+                "--end-screen" => settings.end_screen = true,
+                "--check" => settings.check = true,
+                "--max-length" => {
+                    let length = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|length: &usize| *length > 0)
+                        .ok_or_else(|| {
+                            GameError::Config("--max-length needs a positive length".to_string())
+                        })?;
+                    settings.max_length = Some(length);
+                }
+                "--lives" => {
+                    let lives = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|lives: &u32| *lives > 0)
+                        .ok_or_else(|| {
+                            GameError::Config("--lives needs a positive number".to_string())
+                        })?;
+                    settings.lives = Some(lives);
+                }
+                "--goal" => {
+                    let goal = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|goal: &u32| *goal > 0)
+                        .ok_or_else(|| {
+                            GameError::Config("--goal needs a positive food count".to_string())
+                        })?;
+                    settings.goal_foods = Some(goal);
+                }
+                "--initial-direction" => {
+                    let value = args.next().unwrap_or_default();
+                    settings.initial_direction = Direction::from_name(&value).ok_or_else(|| {
+                        GameError::Config(format!(
+                            "Unknown direction '{}', expected up, down, left or right",
+                            value
+                        ))
+                    })?;
+                }
+                "--cell-padding" => {
+                    let padding = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|padding: &f64| *padding >= 0.0)
+                        .ok_or_else(|| {
+                            GameError::Config(
+                                "--cell-padding needs a non-negative number of pixels".to_string(),
+                            )
+                        })?;
+                    settings.cell_padding = Some(padding);
+                }
+                "--score-mode" => {
+                    let value = args.next().unwrap_or_default();
+                    settings.scoring.mode = ScoreMode::from_name(&value).ok_or_else(|| {
+                        GameError::Config(format!(
+                            "Unknown score mode '{}', expected food, length or formula",
+                            value
+                        ))
+                    })?;
+                }
+                "--layers" => {
+                    let value = args.next().unwrap_or_default();
+                    let layers = value
+                        .split(',')
+                        .map(|name| {
+                            Layer::from_name(name.trim()).ok_or_else(|| {
+                                GameError::Config(format!(
+                                    "Unknown layer '{}', expected some of {}",
+                                    name,
+                                    Layer::NAMES.join(", ")
+                                ))
+                            })
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    settings.layers = Some(layers);
+                }
+                "--score-base" => settings.scoring.base = parse_points(args.next())?,
+                "--score-length-bonus" => {
+                    settings.scoring.length_bonus = parse_points(args.next())?
+                }
+                "--score-speed-bonus" => settings.scoring.speed_bonus = parse_points(args.next())?,
+                "--score-quick-bonus" => settings.scoring.quick_bonus = parse_points(args.next())?,
+                "--score-wrap" => {
+                    settings.scoring.wrap_points = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .ok_or_else(|| {
+                            GameError::Config(
+                                "--score-wrap needs a whole number of points, negative for a penalty"
+                                    .to_string(),
+                            )
+                        })?;
+                }
+                "--score-quick-half-life" => {
+                    settings.scoring.quick_half_life = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|ticks: &u32| *ticks > 0)
+                        .ok_or_else(|| {
+                            GameError::Config(
+                                "--score-quick-half-life needs a positive number of ticks"
+                                    .to_string(),
+                            )
+                        })?;
+                }
+                "--background" => {
+                    let path = args.next().ok_or_else(|| {
+                        GameError::Config("--background needs an image path".to_string())
+                    })?;
+                    settings.background = Some(path);
+                }
+                "--food-delay" => {
+                    settings.food_delay = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .ok_or_else(|| {
+                            GameError::Config("--food-delay needs a number of ticks".to_string())
+                        })?;
+                }
+                "--hunger" => {
+                    let ticks = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|&ticks| ticks > 0)
+                        .ok_or_else(|| {
+                            GameError::Config("--hunger needs a number of ticks".to_string())
+                        })?;
+                    settings.hunger_ticks = Some(ticks);
+                }
+                "--reachable-food" => settings.reachable_food = true,
+                "--starvation-guard" => {
+                    let ticks = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .ok_or_else(|| {
+                            GameError::Config(
+                                "--starvation-guard needs a number of ticks".to_string(),
+                            )
+                        })?;
+                    settings.starvation_ticks = Some(ticks);
+                }
+                "--max-ticks" => {
+                    let ticks = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .ok_or_else(|| {
+                            GameError::Config("--max-ticks needs a number of ticks".to_string())
+                        })?;
+                    settings.max_ticks = Some(ticks);
+                }
+                "--splash" => {
+                    settings.splash_seconds = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|seconds: &f64| *seconds > 0.0)
+                        .ok_or_else(|| {
+                            GameError::Config("--splash needs a number of seconds".to_string())
+                        })?;
+                }
+                "--torus-preview" => settings.torus_preview = true,
+                "--integer-scale" => settings.integer_scale = true,
+                "--margin" => {
+                    settings.margin = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|margin: &f64| *margin >= 0.0)
+                        .ok_or_else(|| {
+                            GameError::Config(
+                                "--margin needs a non-negative number of pixels".to_string(),
+                            )
+                        })?;
+                }
+                "--milestone" | "--major-milestone" => {
+                    let length = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|length: &usize| *length > 1)
+                        .ok_or_else(|| {
+                            GameError::Config(format!("{} needs a snake length above 1", arg))
+                        })?;
+                    settings.milestones.push(Milestone {
+                        length,
+                        major: arg == "--major-milestone",
+                    });
+                }
+                "--fog" => {
+                    let radius = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|radius: &f64| *radius >= 0.0)
+                        .ok_or_else(|| {
+                            GameError::Config(
+                                "--fog needs a non-negative radius in cells".to_string(),
+                            )
+                        })?;
+                    settings.fog_radius = Some(radius);
+                }
+                "--kiosk" => {
+                    let delay = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|delay: &f64| *delay >= 0.0)
+                        .ok_or_else(|| {
+                            GameError::Config(
+                                "--kiosk needs a non-negative number of seconds".to_string(),
+                            )
+                        })?;
+                    settings.kiosk_delay = Some(delay);
+                }
+                "--speed-base" | "--speed-max" => {
+                    let ups = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|ups: &u64| *ups > 0)
+                        .ok_or_else(|| {
+                            GameError::Config(format!(
+                                "{} needs a positive number of ticks per second",
+                                arg
+                            ))
+                        })?;
+                    if arg == "--speed-base" {
+                        settings.speed_curve.base = ups;
+                    } else {
+                        settings.speed_curve.max = ups;
+                    }
+                }
+                "--speed-step" => {
+                    settings.speed_curve.step = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|step: &f64| *step >= 0.0)
+                        .ok_or_else(|| {
+                            GameError::Config(
+                                "--speed-step needs a non-negative number of ticks per second"
+                                    .to_string(),
+                            )
+                        })?;
+                }
+                "--speed-ramp" => {
+                    let value = args.next().unwrap_or_default();
+                    settings.speed_curve.ramp = match value.as_str() {
+                        "linear" => Ramp::Linear,
+                        "exponential" => Ramp::Exponential,
+                        _ => {
+                            return Err(GameError::Config(format!(
+                                "Unknown speed ramp '{}', expected linear or exponential",
+                                value
+                            )))
+                        }
+                    };
+                }
+                "--max-fps" => {
+                    let fps = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|fps: &u64| *fps > 0)
+                        .ok_or_else(|| {
+                            GameError::Config(
+                                "--max-fps needs a positive number of frames".to_string(),
+                            )
+                        })?;
+                    settings.max_fps = Some(fps);
+                }
+                "--msaa" => {
+                    settings.msaa = args
+                        .next()
+                        .and_then(|value| value.parse().ok())
+                        .filter(|samples: &u8| *samples <= 16 && samples.count_ones() <= 1)
+                        .ok_or_else(|| {
+                            GameError::Config(
+                                "--msaa needs 0 or a power of two up to 16".to_string(),
+                            )
+                        })?;
+                }
+                "--theme" => {
+                    let value = args.next().unwrap_or_default();
+                    settings.theme = Theme::from_name(&value).ok_or_else(|| {
+                        GameError::Config(format!(
+                            "Unknown theme '{}', expected classic or nokia",
+                            value
+                        ))
+                    })?;
+                }
+                _ => eprintln!("Ignoring unknown argument: {}", arg),
+            }
+        }
+
+        Ok(settings)
+    }
+
+    fn validate(&self, width: f64, height: f64, item_size: f64) -> Result<(), GameError> {
+        let columns = (width / item_size).floor() as i64;
+        let rows = (height / item_size).floor() as i64;
+        let on_board = |(x, y): (i64, i64)| x >= 0 && x < columns && y >= 0 && y < rows;
+
+        if width % item_size != 0.0 || height % item_size != 0.0 {
+            return Err(GameError::Config(format!(
+                "A {}x{} board doesn't divide into {} pixel cells",
+                width, height, item_size
+            )));
+        }
+
+        if self
+            .cell_padding
+            .is_some_and(|padding| padding * 2.0 >= item_size)
+        {
+            return Err(GameError::Config(format!(
+                "Cell padding must leave room in a {} pixel cell",
+                item_size
+            )));
+        }
+
+        for &(x, y) in &self.food_queue {
+            if !on_board((x, y)) {
+                return Err(GameError::Config(format!(
+                    "Food cell {},{} is off the board",
+                    x, y
+                )));
+            }
+        }
+
+        for &(from, to) in &self.patrols {
+            for (x, y) in [from, to] {
+                if !on_board((x, y)) {
+                    return Err(GameError::Config(format!(
+                        "Patrol cell {},{} is off the board",
+                        x, y
+                    )));
+                }
+            }
+            if from.0 != to.0 && from.1 != to.1 {
+                return Err(GameError::Config(format!(
+                    "Patrol from {},{} to {},{} must stay on one row or column",
+                    from.0, from.1, to.0, to.1
+                )));
+            }
+
+            let on_path = |(x, y): (i64, i64)| {
+                (from.0.min(to.0)..=from.0.max(to.0)).contains(&x)
+                    && (from.1.min(to.1)..=from.1.max(to.1)).contains(&y)
+            };
+            if let Some(&(x, y)) = self.food_queue.iter().find(|&&cell| on_path(cell)) {
+                return Err(GameError::Config(format!(
+                    "Food cell {},{} is on the path of the patrol from {},{} to {},{}",
+                    x, y, from.0, from.1, to.0, to.1
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_cell(value: &str) -> Result<(i64, i64), GameError> {
+    let invalid = || GameError::Config(format!("Invalid cell '{}', expected x,y", value));
+
+    let (x, y) = value.split_once(',').ok_or_else(invalid)?;
+    let x = x.trim().parse().map_err(|_| invalid())?;
+    let y = y.trim().parse().map_err(|_| invalid())?;
+
+    Ok((x, y))
+}
+
+fn parse_points(value: Option<String>) -> Result<u32, GameError> {
+    value.and_then(|value| value.parse().ok()).ok_or_else(|| {
+        GameError::Config("Scoring options need a whole number of points".to_string())
+    })
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+// * Restart timer for kiosk mode, a key on the final screen holds it there and the next key
+// * starts the countdown again
+struct Kiosk {
+    delay: f64,
+    restart_at: Option<f64>,
+    held: bool,
+}
+
+impl Kiosk {
+    fn new(delay: f64) -> Self {
+        Kiosk {
+            delay,
+            restart_at: None,
+            held: false,
+        }
+    }
+
+    // * Checked while the game is over, the countdown starts from the first check
+    fn restart_due(&mut self, now: f64) -> bool {
+        if self.held {
+            return false;
+        }
+        let at = *self.restart_at.get_or_insert(now + self.delay);
+        if now < at {
+            return false;
+        }
+        self.restart_at = None;
+        true
+    }
+
+    fn key_pressed(&mut self) {
+        self.held = !self.held;
+        self.restart_at = None;
+    }
+}
+
+// * What `--check` reports for settings that already passed validation, once the assets are found
+fn check_report(
+    settings: &Settings,
+    width: f64,
+    height: f64,
+    item_size: f64,
+) -> Result<String, GameError> {
+    if let Some(path) = &settings.background {
+        assets::resolve(path)?;
+    }
+
+    Ok(format!(
+        "Configuration OK: {}x{} cell board, {} queued food, {} patrols",
+        width / item_size,
+        height / item_size,
+        settings.food_queue.len(),
+        settings.patrols.len()
+    ))
+}
+
+fn run() -> Result<(), GameError> {
+    let opengl = OpenGL::V3_2;
+
+    const WIDTH: f64 = 300.0;
+    const HEIGHT: f64 = 300.0;
+    const ITEM_SIZE: f64 = 15.0;
+
+    let mut settings = Settings::from_args()?;
+    settings.validate(WIDTH, HEIGHT, ITEM_SIZE)?;
+    // * The preview is drawn in the margin, so it needs room for at least one cell
+    if settings.torus_preview {
+        settings.margin = settings.margin.max(ITEM_SIZE);
+    }
+
+    // * Dry run for scripts, report on the configuration without opening a window
+    if settings.check {
+        println!("{}", check_report(&settings, WIDTH, HEIGHT, ITEM_SIZE)?);
+        return Ok(());
+    }
+
+    // * Creates a window
+    let window_size = [
+        WIDTH + settings.margin * 2.0,
+        HEIGHT + settings.margin * 2.0,
+    ];
+    let window_settings = WindowSettings::new("Snake game", window_size)
+        .graphics_api(opengl)
+        .exit_on_esc(true);
+    let mut window: Window = match window_settings.clone().samples(settings.msaa).build() {
+        Ok(window) => window,
+        // * Not every context offers multisampling, so try again without it
+        Err(err) if settings.msaa > 0 => {
+            eprintln!(
+                "Warning: no {}x anti-aliasing available ({}), continuing without",
+                settings.msaa, err
+            );
+            window_settings
+                .build()
+                .map_err(|err| GameError::Window(err.to_string()))?
+        }
+        Err(err) => return Err(GameError::Window(err.to_string())),
+    };
+
+    let mut gl = GlGraphics::new(opengl);
+    let mut app = App::init(WIDTH, HEIGHT, ITEM_SIZE, &settings);
+    let mut events = Events::new(EventSettings::new()).ups(settings.speed_curve.base);
+    let mut ups = settings.speed_curve.base;
+    if let Some(fps) = settings.max_fps {
+        events.set_max_fps(fps);
+    }
+    let mut title = String::new();
+    // * Kiosk mode leaves the final screen up for a while, then starts a new game by itself
+    let mut kiosk = settings.kiosk_delay.map(Kiosk::new);
+    while let Some(e) = events.next(&mut window) {
+        let current = app.title();
+        if current != title {
+            window.set_title(current.clone());
+            title = current;
+        }
+        let out_of_ticks = settings.max_ticks.is_some_and(|max| app.out_of_ticks(max));
+        let stays_open = settings.kiosk_delay.is_some() || settings.end_screen;
+        if (app.ended && !stays_open) || out_of_ticks {
+            break;
+        }
+        if let (true, Some(kiosk)) = (app.ended, &mut kiosk) {
+            if kiosk.restart_due(app.clock.elapsed) {
+                print_result(&app, settings.dump_board);
+                app = App::init(WIDTH, HEIGHT, ITEM_SIZE, &settings);
+                // * The splash is only for launch, not every kiosk round
+                app.splash_left = 0.0;
+            }
+        }
+        if let Some(cursor) = e.mouse_cursor_args() {
+            app.cursor = Some(cursor);
+        }
+        if e.cursor_args() == Some(false) {
+            app.cursor = None;
+        }
+        if let Some(args) = e.render_args() {
+            app.render(&mut gl, &args);
+        }
+
+        if let Some(args) = e.update_args() {
+            app.update(args.dt)?;
+        }
+        if app.speed != ups {
+            ups = app.speed;
+            events.set_ups(ups);
+        }
+        if let Some(args) = e.button_args() {
+            if args.state == ButtonState::Press {
+                if app.ended {
+                    match &mut kiosk {
+                        Some(kiosk) => kiosk.key_pressed(),
+                        None if settings.end_screen => break,
+                        None => {}
+                    }
+                } else {
+                    app.handle_input(&args.button);
+                }
+            }
+        }
+    }
+
+    if !app.ended && settings.max_ticks.is_some_and(|max| app.out_of_ticks(max)) {
+        println!(
+            "Stopped after {} ticks. Score: {}, length: {}",
+            app.ticks,
+            app.displayed_score(),
+            app.snake.body.len()
+        );
+    }
+    if app.ended {
+        print_result(&app, settings.dump_board);
+    }
+
+    Ok(())
+}
+
+fn print_result(app: &App, dump_board: bool) {
+    if dump_board {
+        print!("{}", app.to_ascii());
+    }
+
+    let outcome = match (app.won, app.death_cause) {
+        (true, _) => "You win!".to_string(),
+        (false, Some(cause)) => format!("Game over! {}.", cause),
+        (false, None) => "Game over!".to_string(),
+    };
+    let score = app.score;
+    if app.scoring.mode == ScoreMode::Formula {
+        println!(
+            "{} Score: {} (food {} + length bonus {} + speed bonus {} + quick bonus {} + challenge {} + wraps {})",
+            outcome,
+            score.total(),
+            score.food,
+            score.length,
+            score.speed,
+            score.quick,
+            score.objective,
+            score.wrap
+        );
+    } else {
+        println!("{} Score: {}", outcome, app.displayed_score());
+    }
+}
+
+// * Whether `target` is in the half of the wrapping board in front of `from`
+fn is_ahead(
+    from: &Position,
+    target: &Position,
+    direction: Direction,
+    width: f64,
+    height: f64,
+) -> bool {
+    let (forward, span) = match direction {
+        Direction::Left => (from.x - target.x, width),
+        Direction::Right => (target.x - from.x, width),
+        Direction::Up => (from.y - target.y, height),
+        Direction::Down => (target.y - from.y, height),
+    };
+    let forward = forward.rem_euclid(span);
+
+    forward > 0.0 && forward <= span / 2.0
+}
+
+// * Where a cell shows up again past the board edges if the board were a torus, only for
+// * cells within `depth` of an edge
+fn torus_ghosts(pos: &Position, width: f64, height: f64, depth: f64) -> Vec<Position> {
+    let shifts = |value: f64, span: f64| {
+        let mut shifts = vec![0.0];
+        if value < depth {
+            shifts.push(span);
+        }
+        if value >= span - depth {
+            shifts.push(-span);
+        }
+        shifts
+    };
+
+    let columns = shifts(pos.x, width);
+    let rows = shifts(pos.y, height);
+    columns
+        .iter()
+        .flat_map(|&dx| rows.iter().map(move |&dy| (dx, dy)))
+        .filter(|&(dx, dy)| dx != 0.0 || dy != 0.0)
+        .map(|(dx, dy)| Position {
+            x: pos.x + dx,
+            y: pos.y + dy,
+        })
+        .collect()
+}
+
+// * How strongly a cell is hidden, clear inside the radius and fading in over the next cell
+fn fog_alpha(distance: f64, radius: f64) -> f32 {
+    const FOG_DENSITY: f64 = 0.85;
+    ((distance - radius).clamp(0.0, 1.0) * FOG_DENSITY) as f32
+}
+
+// * Cell the head moves into on the next tick, wrapping at the edges
+fn next_cell(
+    head: &Position,
+    direction: Direction,
+    width: f64,
+    height: f64,
+    size: f64,
+) -> Position {
+    let (dx, dy) = match direction {
+        Direction::Left => (-size, 0.0),
+        Direction::Right => (size, 0.0),
+        Direction::Up => (0.0, -size),
+        Direction::Down => (0.0, size),
+    };
+
+    Position {
+        x: (head.x + dx).rem_euclid(width),
+        y: (head.y + dy).rem_euclid(height),
+    }
+}
+
+// * Snake colour for each length band, the last one covers everything longer
+const LENGTH_COLORS: [(usize, [f32; 4]); 6] = [
+    (10, [1.0, 0.0, 0.0, 1.0]),
+    (20, [1.0, 0.5, 0.0, 1.0]),
+    (30, [0.9, 0.8, 0.0, 1.0]),
+    (40, [0.0, 0.7, 0.2, 1.0]),
+    (50, [0.0, 0.4, 1.0, 1.0]),
+    (usize::MAX, [0.6, 0.2, 0.9, 1.0]),
+];
+
+fn length_color(length: usize) -> [f32; 4] {
+    LENGTH_COLORS
+        .iter()
+        .find(|(below, _)| length < *below)
+        .map_or(LENGTH_COLORS[LENGTH_COLORS.len() - 1].1, |(_, color)| {
+            *color
+        })
+}
+
+// * Eyes sit side by side against the edge of the head cell it is moving towards
+fn eye_squares(
+    head: &Position,
+    direction: Direction,
+    size: f64,
+) -> [graphics::types::Rectangle; 2] {
+    let eye = size / 5.0;
+    let near = eye;
+    let far = size - eye * 2.0;
+
+    let offsets = match direction {
+        Direction::Left => [(near, near), (near, far)],
+        Direction::Right => [(far, near), (far, far)],
+        Direction::Up => [(near, near), (far, near)],
+        Direction::Down => [(near, far), (far, far)],
+    };
+
+    offsets.map(|(x, y)| [head.x + x, head.y + y, eye, eye])
+}
+
+// * Whether the body turns at `cur`, wrapped runs along a row or column still count as straight
+fn is_corner(prev: &Position, cur: &Position, next: &Position) -> bool {
+    let straight_x = prev.x == cur.x && cur.x == next.x;
+    let straight_y = prev.y == cur.y && cur.y == next.y;
+    !straight_x && !straight_y
+}
+
+// * Shrinks the window viewport by `margin` points on every side, so drawing at
+// * (0, 0) lands inside the margin
+fn board_viewport(viewport: Viewport, margin: f64) -> Viewport {
+    let scale = viewport.draw_size[0] as f64 / viewport.window_size[0];
+    let inset = (margin * scale).round() as i32;
+    let [x, y, width, height] = viewport.rect;
+
+    Viewport {
+        rect: [x + inset, y + inset, width - inset * 2, height - inset * 2],
+        draw_size: viewport.draw_size,
+        window_size: viewport.window_size,
+    }
+}
+
+// * Board coordinates of a point in window points, undoing the board viewport's placement and scale
+fn board_point([x, y]: [f64; 2], window: Viewport, board: Viewport) -> [f64; 2] {
+    let device = window.draw_size[0] as f64 / window.window_size[0];
+    let scale = board.draw_size[0] as f64 / board.window_size[0];
+    let [left, bottom, _, height] = board.rect;
+    // * Viewport rectangles count up from the bottom of the window, the cursor down from the top
+    let top = board.draw_size[1] as i32 - bottom - height;
+
+    [
+        (x * device - left as f64) / scale,
+        (y * device - top as f64) / scale,
+    ]
+}
+
+// * Draws the board and margin at the largest whole number of device pixels per point that
+// * fits the window, centred with the leftover space as bars, so every cell stays crisp
+fn integer_viewport(viewport: Viewport, board: [f64; 2], margin: f64) -> Viewport {
+    let [x, y, width, height] = viewport.rect;
+    let [board_width, board_height] = board;
+    let scale = (width as f64 / (board_width + margin * 2.0))
+        .min(height as f64 / (board_height + margin * 2.0))
+        .floor()
+        .max(1.0);
+    let used_width = (board_width * scale) as i32;
+    let used_height = (board_height * scale) as i32;
+
+    Viewport {
+        rect: [
+            x + (width - used_width) / 2,
+            y + (height - used_height) / 2,
+            used_width,
+            used_height,
+        ],
+        draw_size: viewport.draw_size,
+        // * One point covers `scale` device pixels
+        window_size: [
+            viewport.draw_size[0] as f64 / scale,
+            viewport.draw_size[1] as f64 / scale,
+        ],
+    }
+}
+
+fn cell_square(pos: &Position, size: f64, inset: f64) -> graphics::types::Rectangle {
+    [
+        pos.x + inset,
+        pos.y + inset,
+        size - inset * 2.0,
+        size - inset * 2.0,
+    ]
+}
+
+fn cell_pos(x: i64, y: i64, item_size: f64) -> Position {
+    Position {
+        x: x as f64 * item_size,
+        y: y as f64 * item_size,
+    }
+}
+
+fn random_pos(width: f64, height: f64, item_size: f64) -> Position {
+    let mut rng = rand::thread_rng();
+    let grid_size_height = (height / item_size) - 1.0;
+    let grid_size_height = grid_size_height.floor() as i64;
+    let grid_size_width = (width / item_size) - 1.0;
+    let grid_size_width = grid_size_width.floor() as i64;
+    let rand_x = rng.gen_range(0..grid_size_width) as f64;
+    let rand_y = rng.gen_range(0..grid_size_height) as f64;
+
+    let rand_x = rand_x * item_size;
+    let rand_y = rand_y * item_size;
+
+    Position {
+        x: rand_x,
+        y: rand_y,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: f64 = 300.0;
+    const HEIGHT: f64 = 300.0;
+    const ITEM_SIZE: f64 = 15.0;
+
+    fn cell(x: i64, y: i64) -> Position {
+        cell_pos(x, y, ITEM_SIZE)
+    }
+
+    fn new_app(settings: Settings) -> App {
+        App::init(WIDTH, HEIGHT, ITEM_SIZE, &settings)
+    }
+
+    // * Lays the snake out head first, and parks any food that gets eaten in the bottom left corner
+    fn place(app: &mut App, body: &[(i64, i64)], direction: Direction, food: (i64, i64)) {
+        app.snake.body = body.iter().map(|&(x, y)| cell(x, y)).collect();
+        app.snake.direction = direction;
+        app.food.position = cell(food.0, food.1);
+        app.food.queue = LinkedList::from([cell(0, 19)]);
+        app.food.cycle_queue = true;
+    }
+
+    fn snake(body: &[(i64, i64)], direction: Direction) -> Snake {
+        let mut snake = Snake::new(ITEM_SIZE, cell(0, 0), &Theme::CLASSIC);
+        snake.body = body.iter().map(|&(x, y)| cell(x, y)).collect();
+        snake.direction = direction;
+        snake
+    }
+
+    fn food(x: i64, y: i64) -> Food {
+        Food::new(cell(x, y), ITEM_SIZE, &Theme::CLASSIC)
+    }
+
+    fn parse(args: &[&str]) -> Result<Settings, GameError> {
+        Settings::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    // * Viewport covering a whole window of `points`, at `dpi` device pixels per point
+    fn window(points: [f64; 2], dpi: f64) -> Viewport {
+        let pixels = [(points[0] * dpi) as u32, (points[1] * dpi) as u32];
+        Viewport {
+            rect: [0, 0, pixels[0] as i32, pixels[1] as i32],
+            draw_size: pixels,
+            window_size: points,
+        }
+    }
+
+    fn tick(app: &mut App) {
+        app.update(0.1).unwrap();
+    }
+
+    fn press(app: &mut App, key: Key) {
+        app.handle_input(&Button::Keyboard(key));
+    }
+
+    // * A 2x2 pocket at 5,5 walled in by patrols standing still, see `pocket_walls`
+    const POCKET: [(i64, i64); 4] = [(5, 5), (6, 5), (5, 6), (6, 6)];
+
+    fn pocket_walls() -> Vec<((i64, i64), (i64, i64))> {
+        (4..=7)
+            .flat_map(|x| (4..=7).map(move |y| (x, y)))
+            .filter(|cell| !POCKET.contains(cell))
+            .map(|cell| (cell, cell))
+            .collect()
+    }
+
+    // * Head at 5,5 heading right into its own body
+    const COILED: [(i64, i64); 5] = [(5, 5), (5, 6), (6, 6), (6, 5), (6, 4)];
+
+    #[test]
+    fn validate_rejects_a_board_that_does_not_divide_into_cells() {
+        let settings = Settings::default();
+
+        assert!(matches!(
+            settings.validate(310.0, HEIGHT, ITEM_SIZE),
+            Err(GameError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn validate_rejects_food_off_the_board() {
+        let settings = Settings {
+            food_queue: vec![(20, 3)],
+            ..Settings::default()
+        };
+
+        assert!(matches!(
+            settings.validate(WIDTH, HEIGHT, ITEM_SIZE),
+            Err(GameError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn missing_asset_is_an_asset_error() {
+        assert!(matches!(
+            assets::resolve("missing.png"),
+            Err(GameError::Asset(_))
+        ));
+    }
+
+    #[test]
+    fn direction_works_as_a_hash_map_key() {
+        let mut names = std::collections::HashMap::new();
+        for (direction, name) in [
+            (Direction::Up, "up"),
+            (Direction::Down, "down"),
+            (Direction::Left, "left"),
+            (Direction::Right, "right"),
+        ] {
+            names.insert(direction, name);
+        }
+        names.insert(Direction::Up, "north");
+
+        assert_eq!(names.len(), 4);
+        assert_eq!(names[&Direction::Up], "north");
+        assert_eq!(names[&Direction::Left], "left");
+    }
+
+    #[test]
+    fn queued_food_appears_in_order() {
+        let mut app = new_app(Settings {
+            food_queue: vec![(1, 1), (2, 2), (3, 3)],
+            ..Settings::default()
+        });
+
+        assert_eq!(app.food.position, cell(1, 1));
+        app.respawn_food();
+        assert_eq!(app.food.position, cell(2, 2));
+        app.respawn_food();
+        assert_eq!(app.food.position, cell(3, 3));
+        assert!(app.food.queue.is_empty());
+    }
+
+    #[test]
+    fn validate_rejects_food_on_a_patrol_path() {
+        let settings = Settings {
+            food_queue: vec![(4, 7)],
+            patrols: vec![((2, 7), (9, 7))],
+            ..Settings::default()
+        };
+
+        assert!(matches!(
+            settings.validate(WIDTH, HEIGHT, ITEM_SIZE),
+            Err(GameError::Config(_))
+        ));
+
+        let settings = Settings {
+            food_queue: vec![(4, 8)],
+            ..settings
+        };
+        assert!(settings.validate(WIDTH, HEIGHT, ITEM_SIZE).is_ok());
+    }
+
+    #[test]
+    fn undo_restores_the_body_and_score_from_before_the_last_tick() {
+        let mut app = new_app(Settings {
+            debug: true,
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5), (4, 5)], Direction::Right, (8, 5));
+
+        tick(&mut app);
+        tick(&mut app);
+        let body = app.snake.body.clone();
+        let score = app.score.total();
+
+        tick(&mut app);
+        assert_eq!(app.foods_eaten, 1);
+        assert_ne!(app.score.total(), score);
+
+        press(&mut app, Key::U);
+        assert_eq!(app.snake.body, body);
+        assert_eq!(app.score.total(), score);
+        assert_eq!(app.foods_eaten, 0);
+    }
+
+    #[test]
+    fn undo_needs_debug_mode_even_with_a_death_replay_history() {
+        let mut app = new_app(Settings {
+            death_replay: true,
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (0, 0));
+
+        tick(&mut app);
+        assert!(app
+            .history
+            .as_ref()
+            .is_some_and(|history| !history.is_empty()));
+
+        press(&mut app, Key::U);
+        assert_eq!(app.snake.body.front(), Some(&cell(6, 5)));
+    }
+
+    #[test]
+    fn snake_never_starts_on_a_patrol() {
+        // * Random spots stay off the last row and column, so this leaves exactly one free start
+        let patrols = (0..19)
+            .flat_map(|x| (0..19).map(move |y| (x, y)))
+            .filter(|&cell| cell != (7, 11))
+            .map(|cell| (cell, cell))
+            .collect();
+        let app = new_app(Settings {
+            patrols,
+            ..Settings::default()
+        });
+
+        assert_eq!(app.snake.body.front(), Some(&cell(7, 11)));
+    }
+
+    #[test]
+    fn patrol_kills_the_snake() {
+        let mut app = new_app(Settings {
+            patrols: vec![((6, 5), (6, 5))],
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (0, 0));
+
+        tick(&mut app);
+
+        assert!(app.ended);
+        assert!(app.death_cause == Some(DeathCause::Patrol));
+    }
+
+    #[test]
+    fn turn_grace_applies_a_turn_one_tick_later() {
+        let mut app = new_app(Settings {
+            turn_grace: true,
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (0, 0));
+
+        press(&mut app, Key::Up);
+        tick(&mut app);
+        assert_eq!(app.snake.body.front(), Some(&cell(6, 5)));
+
+        tick(&mut app);
+        assert_eq!(app.snake.body.front(), Some(&cell(6, 4)));
+    }
+
+    #[test]
+    fn ascii_dump_of_a_small_world() {
+        let mut app = App::init(
+            60.0,
+            45.0,
+            ITEM_SIZE,
+            &Settings {
+                patrols: vec![((2, 2), (2, 2))],
+                ..Settings::default()
+            },
+        );
+        place(&mut app, &[(1, 1), (0, 1)], Direction::Right, (3, 0));
+
+        assert_eq!(app.to_ascii(), "...O\n##..\n..X.\n");
+    }
+
+    #[test]
+    fn a_lone_head_may_reverse_but_a_body_may_not() {
+        let mut app = new_app(Settings::default());
+        place(&mut app, &[(5, 5)], Direction::Right, (0, 0));
+        press(&mut app, Key::Left);
+        tick(&mut app);
+        assert_eq!(app.snake.direction, Direction::Left);
+        assert_eq!(app.snake.body.front(), Some(&cell(4, 5)));
+
+        place(&mut app, &[(5, 5), (4, 5)], Direction::Right, (0, 0));
+        press(&mut app, Key::Left);
+        assert!(app.turns.is_empty());
+        tick(&mut app);
+        assert_eq!(app.snake.direction, Direction::Right);
+    }
+
+    #[test]
+    fn length_stays_at_the_cap_while_the_score_rises() {
+        let mut app = new_app(Settings {
+            max_length: Some(2),
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (6, 5));
+
+        for x in 7..10 {
+            tick(&mut app);
+            app.food.position = cell(x, 5);
+        }
+        tick(&mut app);
+
+        assert_eq!(app.foods_eaten, 4);
+        assert_eq!(app.score.total(), 4);
+        assert_eq!(app.snake.body.len(), 2);
+    }
+
+    #[test]
+    fn win_fires_exactly_at_the_food_goal() {
+        let mut app = new_app(Settings {
+            goal_foods: Some(2),
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (6, 5));
+
+        tick(&mut app);
+        assert_eq!(app.foods_eaten, 1);
+        assert!(!app.ended);
+
+        app.food.position = cell(7, 5);
+        tick(&mut app);
+        assert!(app.won);
+        assert!(app.ended);
+    }
+
+    #[test]
+    fn initial_direction_sets_the_heading_and_keeps_food_out_of_the_way() {
+        let settings = parse(&["--initial-direction", "up"]).unwrap();
+        assert_eq!(settings.initial_direction, Direction::Up);
+
+        for _ in 0..50 {
+            let mut app = new_app(parse(&["--initial-direction", "up"]).unwrap());
+            let head = *app.snake.body.front().unwrap();
+            assert_eq!(app.snake.direction, Direction::Up);
+            assert!(!is_ahead(
+                &head,
+                &app.food.position,
+                Direction::Up,
+                WIDTH,
+                HEIGHT
+            ));
+
+            app.food.position = cell(19, 19);
+            tick(&mut app);
+            assert_eq!(
+                app.snake.body.front(),
+                Some(&next_cell(&head, Direction::Up, WIDTH, HEIGHT, ITEM_SIZE))
+            );
+        }
+
+        assert!(matches!(
+            parse(&["--initial-direction", "north"]),
+            Err(GameError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn game_clock_stands_still_while_paused() {
+        let mut clock = GameClock::new();
+        clock.advance(0.5);
+        clock.paused = true;
+        clock.advance(1.0);
+        assert_eq!(clock.elapsed, 0.5);
+        assert_eq!(clock.paused_for, 1.0);
+
+        clock.paused = false;
+        clock.advance(0.25);
+        assert_eq!(clock.elapsed, 0.75);
+        assert_eq!(clock.paused_for, 0.0);
+    }
+
+    #[test]
+    fn pausing_stops_the_snake_and_resuming_carries_on() {
+        let mut app = new_app(Settings::default());
+        place(&mut app, &[(5, 5)], Direction::Right, (0, 0));
+
+        press(&mut app, Key::Space);
+        tick(&mut app);
+        tick(&mut app);
+        assert_eq!(app.ticks, 0);
+        assert_eq!(app.snake.body.front(), Some(&cell(5, 5)));
+
+        press(&mut app, Key::Space);
+        tick(&mut app);
+        assert_eq!(app.ticks, 1);
+        assert_eq!(app.snake.body.front(), Some(&cell(6, 5)));
+    }
+
+    #[test]
+    fn zen_respawns_instead_of_ending() {
+        let mut app = new_app(Settings {
+            zen: true,
+            ..Settings::default()
+        });
+        place(&mut app, &COILED, Direction::Right, (0, 0));
+
+        tick(&mut app);
+
+        assert!(!app.ended);
+        assert!(app.death_cause == Some(DeathCause::SelfCollision));
+        assert_eq!(app.snake.body.len(), 1);
+    }
+
+    #[test]
+    fn cell_square_insets_every_side() {
+        assert_eq!(
+            cell_square(&cell(2, 3), ITEM_SIZE, 2.0),
+            [32.0, 47.0, 11.0, 11.0]
+        );
+        assert_eq!(
+            cell_square(&cell(0, 0), ITEM_SIZE, 0.0),
+            [0.0, 0.0, 15.0, 15.0]
+        );
+    }
+
+    #[test]
+    fn scripted_score_adds_up_every_bonus() {
+        let mut app = new_app(Settings {
+            scoring: Scoring {
+                base: 2,
+                length_bonus: 1,
+                speed_bonus: 1,
+                ..Scoring::default()
+            },
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (6, 5));
+
+        tick(&mut app);
+
+        // * 2 for the food, 1 per segment of the new length 2 and 1 per cell/s at speed 10
+        assert_eq!(app.score.food, 2);
+        assert_eq!(app.score.length, 2);
+        assert_eq!(app.score.speed, 10);
+        assert_eq!(app.score.total(), 14);
+    }
+
+    #[test]
+    fn wrap_penalties_never_take_the_total_below_zero() {
+        let score = Score {
+            food: 3,
+            wrap: -5,
+            ..Score::default()
+        };
+        assert_eq!(score.total(), 0);
+
+        let score = Score { wrap: -2, ..score };
+        assert_eq!(score.total(), 1);
+    }
+
+    #[test]
+    fn upcoming_wrap_spots_the_edge_within_two_cells() {
+        let mut app = new_app(Settings::default());
+
+        place(&mut app, &[(18, 5)], Direction::Right, (0, 0));
+        assert_eq!(app.upcoming_wrap(), Some((cell(19, 5), cell(0, 5))));
+
+        place(&mut app, &[(17, 5)], Direction::Right, (0, 0));
+        assert_eq!(app.upcoming_wrap(), None);
+
+        place(&mut app, &[(0, 3)], Direction::Left, (0, 0));
+        assert_eq!(app.upcoming_wrap(), Some((cell(0, 3), cell(19, 3))));
+
+        place(&mut app, &[(4, 1)], Direction::Up, (0, 0));
+        assert_eq!(app.upcoming_wrap(), Some((cell(4, 0), cell(4, 19))));
+
+        place(&mut app, &[(4, 1)], Direction::Down, (0, 0));
+        assert_eq!(app.upcoming_wrap(), None);
+    }
+
+    #[test]
+    fn food_pop_in_follows_the_game_clock() {
+        let spawned_at = 0.0;
+        let mut clock = GameClock::new();
+
+        clock.paused = true;
+        clock.advance(0.1);
+        assert_eq!(clock.elapsed, 0.0);
+        assert_eq!(spawn_scale(clock.elapsed - spawned_at), 0.0);
+
+        clock.paused = false;
+        clock.advance(0.1);
+        assert_eq!(clock.elapsed, 0.1);
+        assert!((spawn_scale(clock.elapsed - spawned_at) - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn wrapping_into_a_patrol_is_lethal() {
+        let mut app = new_app(Settings {
+            patrols: vec![((0, 5), (0, 5))],
+            ..Settings::default()
+        });
+        place(&mut app, &[(19, 5)], Direction::Right, (10, 10));
+
+        tick(&mut app);
+
+        assert!(app.ended);
+        assert!(app.death_cause == Some(DeathCause::Patrol));
+    }
+
+    #[test]
+    fn is_ahead_covers_the_half_board_in_front_only() {
+        let from = cell(5, 5);
+        let ahead = |target: (i64, i64), direction| {
+            is_ahead(&from, &cell(target.0, target.1), direction, WIDTH, HEIGHT)
+        };
+
+        assert!(ahead((6, 5), Direction::Right));
+        assert!(ahead((15, 9), Direction::Right));
+        assert!(!ahead((4, 5), Direction::Right));
+        assert!(!ahead((5, 5), Direction::Right));
+        assert!(ahead((5, 4), Direction::Up));
+        assert!(ahead((5, 0), Direction::Up));
+        assert!(ahead((5, 15), Direction::Up));
+        assert!(!ahead((5, 6), Direction::Up));
+        assert!(ahead((4, 5), Direction::Left));
+        assert!(ahead((5, 6), Direction::Down));
+    }
+
+    #[test]
+    fn first_food_never_spawns_ahead_of_or_on_the_head() {
+        for direction in [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ] {
+            for _ in 0..50 {
+                let app = new_app(Settings {
+                    initial_direction: direction,
+                    ..Settings::default()
+                });
+                let head = *app.snake.body.front().unwrap();
+
+                assert_ne!(app.food.position, head);
+                assert_ne!(
+                    app.food.position,
+                    next_cell(&head, direction, WIDTH, HEIGHT, ITEM_SIZE)
+                );
+                assert!(!is_ahead(
+                    &head,
+                    &app.food.position,
+                    direction,
+                    WIDTH,
+                    HEIGHT
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn double_food_scores_once_both_cells_are_eaten() {
+        let mut food = Food::new(cell(6, 5), ITEM_SIZE, &Theme::CLASSIC);
+        food.pair = Some(cell(7, 5));
+        assert!(!food.eat(&cell(6, 5)));
+        assert_eq!(food.cells(), vec![cell(7, 5)]);
+        assert!(food.eat(&cell(7, 5)));
+
+        let mut app = new_app(Settings::default());
+        place(&mut app, &[(5, 5)], Direction::Right, (6, 5));
+        app.food.pair = Some(cell(7, 5));
+
+        tick(&mut app);
+        assert_eq!(app.foods_eaten, 0);
+        assert_eq!(app.score.total(), 0);
+
+        tick(&mut app);
+        assert_eq!(app.foods_eaten, 1);
+        assert_eq!(app.snake.body.len(), 3);
+    }
+
+    #[test]
+    fn flip_y_counts_rows_up_from_the_bottom() {
+        let args = ["--food", "3,0", "--food", "4,2", "--patrol", "1,0", "1,2"];
+
+        let app = new_app(parse(&args).unwrap());
+        assert_eq!(app.food.position, cell(3, 0));
+        assert_eq!(app.patrols[0].position, cell(1, 0));
+
+        let mut flipped = vec!["--flip-y"];
+        flipped.extend(args);
+        let mut app = new_app(parse(&flipped).unwrap());
+        assert_eq!(app.food.position, cell(3, 19));
+        assert_eq!(app.patrols[0].position, cell(1, 19));
+        assert_eq!(app.patrols[0].to, cell(1, 17));
+        app.respawn_food();
+        assert_eq!(app.food.position, cell(4, 17));
+    }
+
+    #[test]
+    fn arrow_and_wasd_together_queue_one_turn() {
+        let mut app = new_app(Settings::default());
+        place(
+            &mut app,
+            &[(5, 5), (4, 5), (3, 5)],
+            Direction::Right,
+            (0, 0),
+        );
+
+        press(&mut app, Key::Up);
+        press(&mut app, Key::W);
+        assert_eq!(app.turns.len(), 1);
+
+        // * Down is checked against the queued Up, not the current Right
+        press(&mut app, Key::S);
+        assert_eq!(app.turns.len(), 1);
+
+        press(&mut app, Key::Left);
+        press(&mut app, Key::A);
+        let queued: Vec<Direction> = app.turns.iter().map(|turn| turn.direction).collect();
+        assert_eq!(queued, vec![Direction::Up, Direction::Left]);
+    }
+
+    #[test]
+    fn a_turn_straight_back_is_ignored() {
+        let mut app = new_app(Settings::default());
+        place(
+            &mut app,
+            &[(5, 5), (4, 5), (3, 5)],
+            Direction::Right,
+            (0, 0),
+        );
+
+        press(&mut app, Key::A);
+        assert!(app.turns.is_empty());
+        tick(&mut app);
+        assert_eq!(app.snake.body.front(), Some(&cell(6, 5)));
+    }
+
+    #[test]
+    fn board_viewport_insets_the_margin_in_device_pixels() {
+        let board = board_viewport(window([330.0, 330.0], 1.0), 15.0);
+        assert_eq!(board.rect, [15, 15, 300, 300]);
+
+        let board = board_viewport(window([330.0, 330.0], 2.0), 15.0);
+        assert_eq!(board.rect, [30, 30, 600, 600]);
+        assert_eq!(board.window_size, [330.0, 330.0]);
+
+        let board = board_viewport(window([300.0, 300.0], 1.0), 0.0);
+        assert_eq!(board.rect, [0, 0, 300, 300]);
+    }
+
+    #[test]
+    fn ten_identical_presses_queue_at_most_one_turn() {
+        let mut app = new_app(Settings::default());
+        place(&mut app, &[(5, 5), (4, 5)], Direction::Right, (0, 0));
+
+        for _ in 0..10 {
+            press(&mut app, Key::Up);
+        }
+        assert_eq!(app.turns.len(), 1);
+
+        for _ in 0..10 {
+            press(&mut app, Key::Right);
+        }
+        assert_eq!(app.turns.len(), 2);
+    }
+
+    #[test]
+    fn milestones_show_exactly_one_banner() {
+        let mut app = new_app(Settings {
+            milestones: vec![
+                Milestone {
+                    length: 5,
+                    major: false,
+                },
+                Milestone {
+                    length: 3,
+                    major: true,
+                },
+            ],
+            ..Settings::default()
+        });
+
+        // * Jumping past both milestones at once only announces the longer one
+        app.snake.body = (0..6).map(|x| cell(x, 5)).collect();
+        app.check_milestone();
+        assert_eq!(
+            app.banner.as_ref().map(|banner| banner.text.as_str()),
+            Some("Length 5!")
+        );
+        assert_eq!(app.clock.hold, 0.0);
+
+        app.banner = None;
+        app.check_milestone();
+        app.snake.body.push_back(cell(6, 5));
+        app.check_milestone();
+        assert!(app.banner.is_none());
+    }
+
+    #[test]
+    fn coyote_time_gives_one_tick_to_steer_clear() {
+        let settings = || Settings {
+            coyote_time: true,
+            ..Settings::default()
+        };
+
+        let mut app = new_app(settings());
+        place(&mut app, &COILED, Direction::Right, (0, 0));
+        tick(&mut app);
+        assert!(app.pending_death);
+        assert!(!app.ended);
+        assert_eq!(app.snake.body.front(), Some(&cell(5, 5)));
+
+        press(&mut app, Key::Up);
+        tick(&mut app);
+        assert!(!app.pending_death);
+        assert!(!app.ended);
+        assert_eq!(app.snake.body.front(), Some(&cell(5, 4)));
+
+        let mut app = new_app(settings());
+        place(&mut app, &COILED, Direction::Right, (0, 0));
+        tick(&mut app);
+        tick(&mut app);
+        assert!(app.ended);
+        assert!(app.death_cause == Some(DeathCause::SelfCollision));
+    }
+
+    #[test]
+    fn checker_shade_alternates_by_cell_parity() {
+        let theme = Theme::CLASSIC;
+        let dark = [0.93, 0.93, 0.93, 1.0];
+
+        assert_eq!(theme.checker_shade(0, 0), theme.background);
+        assert_eq!(theme.checker_shade(3, 5), theme.background);
+        assert_eq!(theme.checker_shade(1, 0), dark);
+        assert_eq!(theme.checker_shade(0, 7), dark);
+        assert_eq!(theme.checker_shade(-1, 0), dark);
+    }
+
+    #[test]
+    fn fog_is_clear_inside_the_radius_and_fades_over_one_cell() {
+        assert_eq!(fog_alpha(0.0, 3.0), 0.0);
+        assert_eq!(fog_alpha(3.0, 3.0), 0.0);
+        assert!((fog_alpha(3.5, 3.0) - 0.425).abs() < 1e-6);
+        assert!((fog_alpha(4.0, 3.0) - 0.85).abs() < 1e-6);
+        assert!((fog_alpha(12.0, 3.0) - 0.85).abs() < 1e-6);
+    }
+
+    #[test]
+    fn check_rejects_an_invalid_level_and_board() {
+        let diagonal = parse(&["--check", "--patrol", "1,1", "3,3"]).unwrap();
+        assert!(matches!(
+            diagonal.validate(WIDTH, HEIGHT, ITEM_SIZE),
+            Err(GameError::Config(_))
+        ));
+
+        let settings = parse(&["--check", "--food", "3,4"]).unwrap();
+        assert!(matches!(
+            settings.validate(WIDTH, 301.0, ITEM_SIZE),
+            Err(GameError::Config(_))
+        ));
+        assert!(settings.validate(WIDTH, HEIGHT, ITEM_SIZE).is_ok());
+        assert_eq!(
+            check_report(&settings, WIDTH, HEIGHT, ITEM_SIZE).unwrap(),
+            "Configuration OK: 20x20 cell board, 1 queued food, 0 patrols"
+        );
+
+        let settings = parse(&["--check", "--background", "missing.png"]).unwrap();
+        assert!(matches!(
+            check_report(&settings, WIDTH, HEIGHT, ITEM_SIZE),
+            Err(GameError::Asset(_))
+        ));
+    }
+
+    #[test]
+    fn biting_cuts_the_body_off_at_the_bitten_segment() {
+        let mut bitten = snake(
+            &[(5, 5), (5, 6), (6, 6), (6, 5), (6, 4), (6, 3)],
+            Direction::Right,
+        );
+        bitten.shrink_on_bite = true;
+
+        let result = bitten.update(&food(0, 0), WIDTH, HEIGHT).unwrap();
+
+        assert!(matches!(result, SnakeMoveResult::Bitten(3)));
+        let body: Vec<Position> = bitten.body.into_iter().collect();
+        assert_eq!(body, vec![cell(6, 5), cell(5, 5), cell(5, 6)]);
+    }
+
+    #[test]
+    fn a_bite_takes_its_food_points_with_it() {
+        let mut app = new_app(Settings {
+            shrink_on_bite: true,
+            ..Settings::default()
+        });
+        place(&mut app, &COILED, Direction::Right, (0, 0));
+        app.score.food = 10;
+
+        tick(&mut app);
+
+        assert!(!app.ended);
+        assert_eq!(app.snake.body.len(), 3);
+        assert_eq!(app.score.food, 8);
+    }
+
+    #[test]
+    fn kiosk_restarts_after_the_delay() {
+        let mut kiosk = Kiosk::new(2.0);
+
+        assert!(!kiosk.restart_due(10.0));
+        assert!(!kiosk.restart_due(11.9));
+        assert!(kiosk.restart_due(12.0));
+
+        // * The next game over counts down from scratch
+        assert!(!kiosk.restart_due(20.0));
+        assert!(kiosk.restart_due(22.0));
+    }
+
+    #[test]
+    fn kiosk_key_holds_the_final_screen_and_the_next_key_rearms_it() {
+        let mut kiosk = Kiosk::new(2.0);
+        assert!(!kiosk.restart_due(10.0));
+
+        kiosk.key_pressed();
+        assert!(!kiosk.restart_due(12.0));
+        assert!(!kiosk.restart_due(100.0));
+
+        kiosk.key_pressed();
+        assert!(!kiosk.restart_due(101.0));
+        assert!(kiosk.restart_due(103.0));
+    }
+
+    #[test]
+    fn displayed_score_follows_the_score_mode() {
+        let mut app = new_app(Settings::default());
+        place(
+            &mut app,
+            &[(5, 5), (4, 5), (3, 5), (2, 5), (1, 5)],
+            Direction::Right,
+            (0, 0),
+        );
+        app.foods_eaten = 3;
+        app.score.food = 7;
+
+        app.scoring.mode = ScoreMode::FoodCount;
+        assert_eq!(app.displayed_score(), 3);
+        app.scoring.mode = ScoreMode::Length;
+        assert_eq!(app.displayed_score(), 4);
+        app.scoring.mode = ScoreMode::Formula;
+        assert_eq!(app.displayed_score(), 7);
+    }
+
+    #[test]
+    fn tail_eats_food_left_on_the_new_tail_cell() {
+        let mut eater = snake(&[(5, 5), (4, 5), (3, 5)], Direction::Right);
+        eater.tail_eats = true;
+
+        let result = eater.update(&food(4, 5), WIDTH, HEIGHT).unwrap();
+
+        assert!(matches!(result, SnakeMoveResult::Food(pos) if pos == cell(4, 5)));
+        assert_eq!(eater.pending_growth, 1);
+        assert_eq!(eater.body.back(), Some(&cell(4, 5)));
+
+        let mut plain = snake(&[(5, 5), (4, 5), (3, 5)], Direction::Right);
+        let result = plain.update(&food(4, 5), WIDTH, HEIGHT).unwrap();
+        assert!(matches!(result, SnakeMoveResult::Ok));
+    }
+
+    #[test]
+    fn msaa_takes_zero_or_a_power_of_two_up_to_sixteen() {
+        for samples in ["0", "1", "2", "4", "8", "16"] {
+            let settings = parse(&["--msaa", samples]).unwrap();
+            assert_eq!(settings.msaa.to_string(), samples);
+        }
+        for samples in ["3", "6", "32", "-4", "four"] {
+            assert!(matches!(
+                parse(&["--msaa", samples]),
+                Err(GameError::Config(_))
+            ));
+        }
+        assert!(matches!(parse(&["--msaa"]), Err(GameError::Config(_))));
+    }
+
+    #[test]
+    fn food_reaches_full_size_after_the_pop_in() {
+        assert_eq!(spawn_scale(0.0), 0.0);
+        assert!(spawn_scale(FOOD_POP_SECONDS / 2.0) < 1.0);
+        assert_eq!(spawn_scale(FOOD_POP_SECONDS), 1.0);
+        assert_eq!(spawn_scale(FOOD_POP_SECONDS * 10.0), 1.0);
+
+        let ages = (0..=10).map(|step| step as f64 * FOOD_POP_SECONDS / 10.0);
+        let scales: Vec<f64> = ages.map(spawn_scale).collect();
+        assert!(scales.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn the_game_ends_only_after_the_last_life() {
+        let mut app = new_app(Settings {
+            lives: Some(2),
+            ..Settings::default()
+        });
+        app.score.food = 5;
+
+        place(&mut app, &COILED, Direction::Right, (0, 0));
+        tick(&mut app);
+        assert!(!app.ended);
+        assert_eq!(app.lives, Some(1));
+        assert_eq!(app.snake.body.len(), 1);
+        assert_eq!(app.score.food, 5);
+
+        place(&mut app, &COILED, Direction::Right, (0, 0));
+        tick(&mut app);
+        assert!(app.ended);
+        assert_eq!(app.lives, Some(0));
+    }
+
+    #[test]
+    fn flood_fills_around_walls_and_the_body() {
+        let mut app = new_app(Settings {
+            patrols: pocket_walls(),
+            ..Settings::default()
+        });
+        place(&mut app, &[(10, 10)], Direction::Right, (0, 0));
+
+        // * Everything but the 12 walls, the 4 pocket cells and the head
+        assert_eq!(app.reachable_cells(cell(10, 10)), 400 - 12 - 4 - 1);
+        assert_eq!(app.reachable_cells(cell(5, 5)), 4);
+
+        let flood = app.flood(cell(10, 10));
+        assert!(flood.reaches(&cell(0, 0)));
+        assert!(!flood.reaches(&cell(6, 6)));
+        assert!(!flood.reaches(&cell(4, 4)));
+        assert!(!flood.reaches(&cell(10, 10)));
+    }
+
+    #[test]
+    fn flood_finds_nothing_from_a_boxed_in_head() {
+        let mut app = new_app(Settings::default());
+        place(
+            &mut app,
+            &[
+                (5, 5),
+                (6, 5),
+                (6, 6),
+                (5, 6),
+                (4, 6),
+                (4, 5),
+                (4, 4),
+                (5, 4),
+            ],
+            Direction::Up,
+            (0, 0),
+        );
+
+        assert_eq!(app.reachable_cells(cell(5, 5)), 0);
+        assert!(app.safe_path().is_empty());
+    }
+
+    #[test]
+    fn safe_path_goes_around_a_wall() {
+        let mut app = new_app(Settings {
+            patrols: vec![((6, 9), (6, 9)), ((6, 10), (6, 10)), ((6, 11), (6, 11))],
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 10)], Direction::Right, (7, 10));
+
+        let path = app.safe_path();
+
+        assert_eq!(path.len(), 6);
+        assert_eq!(path.last(), Some(&cell(7, 10)));
+        assert!(app
+            .patrols
+            .iter()
+            .all(|patrol| !path.contains(&patrol.position)));
+    }
+
+    #[test]
+    fn max_fps_is_wired_into_the_settings() {
+        assert_eq!(Settings::default().max_fps, None);
+        assert_eq!(parse(&["--max-fps", "30"]).unwrap().max_fps, Some(30));
+        for fps in ["0", "-5", "fast"] {
+            assert!(matches!(
+                parse(&["--max-fps", fps]),
+                Err(GameError::Config(_))
+            ));
+        }
+        assert!(matches!(parse(&["--max-fps"]), Err(GameError::Config(_))));
+    }
+
+    #[test]
+    fn reach_length_objective_completes_at_the_length() {
+        let mut app = new_app(Settings::default());
+        app.objective = Some(Objective::ReachLength(3));
+        place(&mut app, &[(5, 5), (4, 5)], Direction::Right, (0, 0));
+
+        app.check_objective();
+        assert!(!app.objective_done);
+        assert_eq!(app.score.objective, 0);
+
+        app.snake.body.push_back(cell(3, 5));
+        app.check_objective();
+        assert!(app.objective_done);
+        assert_eq!(app.score.objective, OBJECTIVE_BONUS);
+
+        // * The bonus is only paid once
+        app.snake.body.push_back(cell(2, 5));
+        app.check_objective();
+        assert_eq!(app.score.objective, OBJECTIVE_BONUS);
+    }
+
+    #[test]
+    fn straight_runs_are_not_corners_even_across_a_wrap() {
+        let corner = |a: (i64, i64), b: (i64, i64), c: (i64, i64)| {
+            is_corner(&cell(a.0, a.1), &cell(b.0, b.1), &cell(c.0, c.1))
+        };
+
+        assert!(!corner((4, 5), (5, 5), (6, 5)));
+        assert!(!corner((5, 4), (5, 5), (5, 6)));
+        assert!(!corner((19, 5), (0, 5), (1, 5)));
+        assert!(corner((4, 5), (5, 5), (5, 6)));
+        assert!(corner((5, 4), (5, 5), (6, 5)));
+    }
+
+    #[test]
+    fn input_latency_is_one_tick_per_turn() {
+        let mut app = new_app(Settings {
+            debug: true,
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (0, 0));
+
+        tick(&mut app);
+        press(&mut app, Key::Up);
+        tick(&mut app);
+        assert_eq!(app.input_latency, Some((1, 1)));
+
+        press(&mut app, Key::Left);
+        tick(&mut app);
+        tick(&mut app);
+        assert_eq!(app.input_latency, Some((2, 2)));
+    }
+
+    #[test]
+    fn quick_award_halves_every_half_life() {
+        let scoring = Scoring {
+            quick_bonus: 8,
+            quick_half_life: 10,
+            ..Scoring::default()
+        };
+
+        assert_eq!(scoring.quick_award(0), 8);
+        assert_eq!(scoring.quick_award(10), 4);
+        assert_eq!(scoring.quick_award(20), 2);
+        assert_eq!(scoring.quick_award(5), 6);
+        assert_eq!(scoring.quick_award(1000), 0);
+    }
+
+    #[test]
+    fn wrapping_into_the_body_is_told_apart_from_a_plain_collision() {
+        let mut wrapped = snake(
+            &[(19, 3), (19, 4), (0, 4), (0, 3), (1, 3)],
+            Direction::Right,
+        );
+        let result = wrapped.update(&food(10, 10), WIDTH, HEIGHT).unwrap();
+        assert!(matches!(
+            result,
+            SnakeMoveResult::End(pos, DeathCause::WrapIntoSelf) if pos == cell(0, 3)
+        ));
+
+        let mut coiled = snake(&COILED, Direction::Right);
+        let result = coiled.update(&food(10, 10), WIDTH, HEIGHT).unwrap();
+        assert!(matches!(
+            result,
+            SnakeMoveResult::End(pos, DeathCause::SelfCollision) if pos == cell(6, 5)
+        ));
+    }
+
+    #[test]
+    fn linear_speed_steps_up_per_food_until_the_cap() {
+        let curve = SpeedCurve {
+            base: 10,
+            step: 2.0,
+            max: 30,
+            ramp: Ramp::Linear,
+        };
+
+        assert_eq!(curve.speed_at(0), 10);
+        assert_eq!(curve.speed_at(3), 16);
+        assert_eq!(curve.speed_at(10), 30);
+        assert_eq!(curve.speed_at(50), 30);
+
+        let slow = SpeedCurve { step: 0.5, ..curve };
+        assert_eq!(slow.speed_at(1), 10);
+        assert_eq!(slow.speed_at(2), 11);
+    }
+
+    #[test]
+    fn exponential_speed_compounds_per_food() {
+        let curve = SpeedCurve {
+            base: 10,
+            step: 1.0,
+            max: 30,
+            ramp: Ramp::Exponential,
+        };
+
+        assert_eq!(curve.speed_at(0), 10);
+        assert_eq!(curve.speed_at(1), 11);
+        // * 10 * 1.1^5 is 16.1
+        assert_eq!(curve.speed_at(5), 16);
+        assert_eq!(curve.speed_at(100), 30);
+
+        let capped_below_base = SpeedCurve { max: 5, ..curve };
+        assert_eq!(capped_below_base.speed_at(3), 10);
+    }
+
+    #[test]
+    fn next_cell_steps_one_cell_and_wraps_at_the_edges() {
+        let next = |from: (i64, i64), direction| {
+            next_cell(&cell(from.0, from.1), direction, WIDTH, HEIGHT, ITEM_SIZE)
+        };
+
+        assert_eq!(next((5, 5), Direction::Up), cell(5, 4));
+        assert_eq!(next((5, 5), Direction::Down), cell(5, 6));
+        assert_eq!(next((19, 3), Direction::Right), cell(0, 3));
+        assert_eq!(next((0, 3), Direction::Left), cell(19, 3));
+        assert_eq!(next((7, 0), Direction::Up), cell(7, 19));
+        assert_eq!(next((7, 19), Direction::Down), cell(7, 0));
+    }
+
+    #[test]
+    fn delayed_food_reappears_exactly_after_the_delay() {
+        let mut app = new_app(Settings {
+            food_delay: 3,
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (6, 5));
+
+        tick(&mut app);
+        assert_eq!(app.foods_eaten, 1);
+        assert!(app.food.hidden);
+
+        tick(&mut app);
+        tick(&mut app);
+        assert!(app.food.hidden);
+
+        tick(&mut app);
+        assert!(!app.food.hidden);
+        assert_eq!(app.food.position, cell(0, 19));
+    }
+
+    #[test]
+    fn nothing_moves_until_the_first_key() {
+        let mut app = new_app(Settings {
+            start_on_input: true,
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (0, 0));
+
+        for _ in 0..3 {
+            tick(&mut app);
+        }
+        assert_eq!(app.ticks, 0);
+        assert_eq!(app.snake.body.front(), Some(&cell(5, 5)));
+
+        press(&mut app, Key::Down);
+        assert!(!app.waiting_for_input);
+        tick(&mut app);
+        assert_eq!(app.snake.body.front(), Some(&cell(5, 6)));
+    }
+
+    #[test]
+    fn inverted_controls_turn_up_into_down() {
+        let mut app = new_app(Settings {
+            inverted_controls: true,
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5), (4, 5)], Direction::Right, (0, 0));
+
+        press(&mut app, Key::Up);
+        tick(&mut app);
+        assert_eq!(app.snake.direction, Direction::Down);
+        assert_eq!(app.snake.body.front(), Some(&cell(5, 6)));
+
+        // * Down inverts to Up, straight back into the body, so it's ignored
+        press(&mut app, Key::Down);
+        assert!(app.turns.is_empty());
+    }
+
+    #[test]
+    fn big_food_only_lands_on_a_free_block() {
+        // * A 4x4 board with patrols everywhere but the block at 0,0 and the head at 3,3
+        let free = [(0, 0), (1, 0), (0, 1), (1, 1), (3, 3)];
+        let patrols: Vec<_> = (0..4)
+            .flat_map(|x| (0..4).map(move |y| (x, y)))
+            .filter(|cell| !free.contains(cell))
+            .map(|cell| (cell, cell))
+            .collect();
+        let mut app = App::init(
+            60.0,
+            60.0,
+            ITEM_SIZE,
+            &Settings {
+                patrols,
+                big_food: true,
+                ..Settings::default()
+            },
+        );
+        place(&mut app, &[(3, 3)], Direction::Right, (0, 0));
+        app.food.queue.clear();
+
+        let walls: Vec<Position> = app.patrols.iter().map(|patrol| patrol.position).collect();
+        let taken = |pos: &Position| walls.contains(pos) || *pos == cell(3, 3);
+        assert_eq!(free_blocks(60.0, 60.0, ITEM_SIZE, taken), vec![cell(0, 0)]);
+
+        let mut blocks = 0;
+        for _ in 0..200 {
+            app.respawn_food();
+            if app.food.block {
+                blocks += 1;
+                assert_eq!(app.food.position, cell(0, 0));
+            }
+            assert!(app.food.cells().iter().all(|pos| !taken(pos)));
+        }
+        assert!(blocks > 0);
+    }
+
+    #[test]
+    fn big_food_grows_the_snake_by_four() {
+        let mut app = new_app(Settings {
+            food_delay: 10,
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (6, 5));
+        app.food.block = true;
+
+        tick(&mut app);
+        assert_eq!(app.score.total(), BIG_FOOD_MULTIPLIER);
+        assert_eq!(app.snake.pending_growth, 3);
+
+        for _ in 0..4 {
+            tick(&mut app);
+        }
+        assert_eq!(app.snake.body.len(), 5);
+        assert_eq!(app.snake.pending_growth, 0);
+    }
+
+    #[test]
+    fn each_wrap_counts_and_scores_its_points() {
+        let mut app = new_app(Settings {
+            scoring: Scoring {
+                wrap_points: -2,
+                ..Scoring::default()
+            },
+            ..Settings::default()
+        });
+        place(&mut app, &[(18, 5)], Direction::Right, (10, 10));
+        app.score.food = 3;
+        app.foods_since_wrap = 4;
+
+        tick(&mut app);
+        assert_eq!(app.snake.wraps, 0);
+        assert_eq!(app.foods_since_wrap, 4);
+
+        tick(&mut app);
+        assert_eq!(app.snake.wraps, 1);
+        assert_eq!(app.score.wrap, -2);
+        assert_eq!(app.score.total(), 1);
+        assert_eq!(app.foods_since_wrap, 0);
+
+        tick(&mut app);
+        assert_eq!(app.snake.wraps, 1);
+        assert_eq!(app.score.wrap, -2);
+    }
+
+    #[test]
+    fn length_color_changes_every_ten_segments() {
+        let tier = |index: usize| LENGTH_COLORS[index].1;
+
+        assert_eq!(length_color(1), tier(0));
+        assert_eq!(length_color(9), tier(0));
+        assert_eq!(length_color(10), tier(1));
+        assert_eq!(length_color(29), tier(2));
+        assert_eq!(length_color(49), tier(4));
+        assert_eq!(length_color(50), tier(5));
+        assert_eq!(length_color(400), tier(5));
+    }
+
+    #[test]
+    fn food_on_the_far_edge_is_eaten_through_the_wrap() {
+        let mut eater = snake(&[(19, 3), (18, 3)], Direction::Right);
+
+        let result = eater.update(&food(0, 3), WIDTH, HEIGHT).unwrap();
+
+        assert!(matches!(result, SnakeMoveResult::Food(pos) if pos == cell(0, 3)));
+        assert_eq!(eater.body.len(), 3);
+        assert_eq!(eater.wraps, 1);
+    }
+
+    #[test]
+    fn speed_readout_follows_the_tick_rate() {
+        assert_eq!(new_app(Settings::default()).speed_readout(), None);
+
+        let mut app = new_app(Settings {
+            show_speed: true,
+            speed_curve: SpeedCurve {
+                step: 2.0,
+                ..SpeedCurve::default()
+            },
+            ..Settings::default()
+        });
+        assert_eq!(app.speed_readout().as_deref(), Some("10.0 cells/s"));
+
+        place(&mut app, &[(5, 5)], Direction::Right, (6, 5));
+        tick(&mut app);
+        assert_eq!(app.speed_readout().as_deref(), Some("12.0 cells/s"));
+    }
+
+    #[test]
+    fn stats_break_the_game_down_line_by_line() {
+        let mut app = new_app(Settings::default());
+        place(&mut app, &[(18, 5)], Direction::Right, (19, 5));
+        tick(&mut app);
+        tick(&mut app);
+        app.clock.elapsed = 12.7;
+        app.game_over(cell(0, 5), DeathCause::Patrol);
+
+        assert!(app.ended);
+        assert_eq!(
+            app.stats().lines(),
+            vec![
+                "Game over!",
+                "Score: 1",
+                "Length: 2",
+                "Time: 12s",
+                "Food eaten: 1",
+                "Wraps: 1",
+                "Hit a patrol",
+            ]
+        );
+
+        let won = GameStats {
+            won: true,
+            ..app.stats()
+        };
+        let lines = won.lines();
+        assert_eq!(lines[0], "You win!");
+        assert_eq!(lines.len(), 6);
+    }
+
+    #[test]
+    fn food_is_pushed_along_until_it_meets_a_wall() {
+        let mut app = new_app(Settings {
+            push_food: true,
+            ..Settings::default()
+        });
+
+        place(&mut app, &[(5, 5)], Direction::Right, (6, 5));
+        tick(&mut app);
+        assert_eq!(app.food.position, cell(7, 5));
+        assert_eq!(app.foods_eaten, 0);
+
+        place(&mut app, &[(18, 5)], Direction::Right, (19, 5));
+        tick(&mut app);
+        assert_eq!(app.foods_eaten, 1);
+        assert_eq!(app.snake.body.front(), Some(&cell(19, 5)));
+    }
+
+    #[test]
+    fn restoring_a_scenario_brings_back_the_exact_state() {
+        let mut app = new_app(Settings {
+            practice: true,
+            food_delay: 5,
+            speed_curve: SpeedCurve {
+                step: 1.0,
+                ..SpeedCurve::default()
+            },
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (6, 5));
+        app.food.block = true;
+        app.food.spawned_at = 0.4;
+        press(&mut app, Key::P);
+
+        tick(&mut app);
+        assert!(app.food.hidden);
+        assert_eq!(app.snake.pending_growth, 3);
+        assert_eq!(app.speed, 11);
+
+        press(&mut app, Key::Backspace);
+        assert!(!app.food.hidden);
+        assert!(app.food.block);
+        assert_eq!(app.food.position, cell(6, 5));
+        assert_eq!(app.food.spawned_at, 0.4);
+        assert_eq!(app.snake.pending_growth, 0);
+        assert_eq!(app.snake.body, LinkedList::from([cell(5, 5)]));
+        assert_eq!(app.speed, 10);
+        assert_eq!(app.score.total(), 0);
+    }
+
+    #[test]
+    fn undo_keeps_hidden_food_hidden() {
+        let mut app = new_app(Settings {
+            debug: true,
+            food_delay: 5,
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (6, 5));
+
+        tick(&mut app);
+        assert!(app.food.hidden);
+        let respawn_in = app.food_respawn_in;
+        tick(&mut app);
+        assert_eq!(app.food_respawn_in, respawn_in - 1);
+
+        // * Undo goes back to before the countdown step too
+        press(&mut app, Key::U);
+        assert!(app.food.hidden);
+        assert_eq!(app.food_respawn_in, respawn_in);
+        assert_eq!(app.snake.body.front(), Some(&cell(6, 5)));
+    }
+
+    #[test]
+    fn undoing_the_respawn_tick_hides_the_food_again() {
+        let mut app = new_app(Settings {
+            debug: true,
+            food_delay: 2,
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (6, 5));
+
+        tick(&mut app);
+        tick(&mut app);
+        assert_eq!(app.food_respawn_in, 1);
+        tick(&mut app);
+        assert!(!app.food.hidden);
+
+        press(&mut app, Key::U);
+        assert!(app.food.hidden);
+        assert_eq!(app.food_respawn_in, 1);
+    }
+
+    #[test]
+    fn starvation_guard_moves_food_the_head_cannot_reach() {
+        let mut app = new_app(Settings {
+            patrols: pocket_walls(),
+            starvation_ticks: Some(2),
+            ..Settings::default()
+        });
+        place(&mut app, &[(10, 12)], Direction::Right, (5, 5));
+        let in_pocket = |app: &App| POCKET.iter().any(|&(x, y)| app.food.covers(&cell(x, y)));
+
+        tick(&mut app);
+        tick(&mut app);
+        assert!(in_pocket(&app));
+        assert_eq!(app.unreachable_ticks, 2);
+
+        tick(&mut app);
+        assert!(!in_pocket(&app));
+        assert_eq!(app.unreachable_ticks, 0);
+        let head = *app.snake.body.front().unwrap();
+        assert!(app
+            .food
+            .cells()
+            .iter()
+            .all(|pos| app.flood(head).reaches(pos)));
+    }
+
+    #[test]
+    fn starvation_guard_brings_food_to_a_snake_shut_in_a_lane() {
+        // * Rows 4 and 6 are walled off, leaving row 5 as a loop the snake can only circle
+        let walls = (0..20)
+            .flat_map(|x| [(x, 4), (x, 6)])
+            .map(|cell| (cell, cell))
+            .collect();
+        let mut app = new_app(Settings {
+            patrols: walls,
+            starvation_ticks: Some(3),
+            ..Settings::default()
+        });
+        place(&mut app, &[(2, 5), (1, 5)], Direction::Right, (10, 10));
+
+        for _ in 0..3 {
+            tick(&mut app);
+            assert_eq!(app.food.position, cell(10, 10));
+        }
+        tick(&mut app);
+
+        assert!(!app.ended);
+        assert!(app.food.position.y == cell(0, 5).y || app.foods_eaten == 1);
+    }
+
+    #[test]
+    fn turning_a_layer_off_removes_its_draw_calls() {
+        let app = new_app(parse(&["--checkerboard", "--show-next-cell"]).unwrap());
+        let drawn = app.drawn_layers();
+        assert!(drawn.contains(&Layer::Checkerboard));
+        assert!(drawn.contains(&Layer::NextCell));
+        assert!(!drawn.contains(&Layer::Fog));
+
+        let app = new_app(
+            parse(&[
+                "--checkerboard",
+                "--show-next-cell",
+                "--layers",
+                "background,snake,food",
+            ])
+            .unwrap(),
+        );
+        let drawn = app.drawn_layers();
+        assert!(!drawn.contains(&Layer::Checkerboard));
+        assert!(!drawn.contains(&Layer::NextCell));
+        assert!(drawn.contains(&Layer::Background) && drawn.contains(&Layer::Food));
+        assert_eq!(drawn.len(), 3);
+    }
+
+    #[test]
+    fn hunger_shrinks_the_snake_and_food_resets_it() {
+        let mut app = new_app(Settings {
+            hunger_ticks: Some(3),
+            ..Settings::default()
+        });
+        place(
+            &mut app,
+            &[(5, 5), (4, 5), (3, 5)],
+            Direction::Right,
+            (0, 0),
+        );
+
+        tick(&mut app);
+        tick(&mut app);
+        assert_eq!(app.snake.body.len(), 3);
+        tick(&mut app);
+        assert_eq!(app.snake.body.len(), 2);
+
+        // * Eating on the next tick starts the count again
+        app.food.position = cell(9, 5);
+        tick(&mut app);
+        assert_eq!(app.snake.body.len(), 3);
+        tick(&mut app);
+        tick(&mut app);
+        assert_eq!(app.snake.body.len(), 3);
+        tick(&mut app);
+        assert_eq!(app.snake.body.len(), 2);
+    }
+
+    #[test]
+    fn hunger_ends_the_game_once_nothing_is_left() {
+        let mut app = new_app(Settings {
+            hunger_ticks: Some(1),
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (0, 0));
+
+        tick(&mut app);
+
+        assert!(app.ended);
+        assert!(app.death_cause == Some(DeathCause::Starved));
+    }
+
+    #[test]
+    fn integer_viewport_uses_whole_pixel_scales_and_centres_the_board() {
+        let board = [WIDTH, HEIGHT];
+
+        let exact = integer_viewport(window([300.0, 300.0], 1.0), board, 0.0);
+        assert_eq!(exact.rect, [0, 0, 300, 300]);
+        assert_eq!(exact.window_size, [300.0, 300.0]);
+
+        // * 700x650 fits 2x, leaving 100 and 50 pixel bars split evenly
+        let wide = integer_viewport(window([700.0, 650.0], 1.0), board, 0.0);
+        assert_eq!(wide.rect, [50, 25, 600, 600]);
+        assert_eq!(wide.window_size, [350.0, 325.0]);
+
+        // * The margin needs room too, 900 over a 330 point window only fits 2x
+        let margin = integer_viewport(window([900.0, 900.0], 1.0), board, 15.0);
+        assert_eq!(margin.rect, [150, 150, 600, 600]);
+
+        // * A window smaller than the board still draws at 1x
+        let small = integer_viewport(window([200.0, 200.0], 1.0), board, 0.0);
+        assert_eq!(small.rect, [-50, -50, 300, 300]);
+
+        let hidpi = integer_viewport(window([330.0, 330.0], 2.0), board, 15.0);
+        assert_eq!(hidpi.rect, [30, 30, 600, 600]);
+        assert_eq!(hidpi.window_size, [330.0, 330.0]);
+    }
+
+    #[test]
+    fn co_op_players_steer_on_alternate_ticks() {
+        let mut app = new_app(Settings {
+            co_op: true,
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (0, 0));
+
+        // * Arrows are the first player, WASD the second
+        press(&mut app, Key::Up);
+        press(&mut app, Key::S);
+
+        // * Tick 1 is odd, so the second player's turn comes first
+        tick(&mut app);
+        assert_eq!(app.snake.direction, Direction::Down);
+        tick(&mut app);
+        assert_eq!(app.snake.direction, Direction::Up);
+        assert_eq!(app.co_op, Some([None, None]));
+    }
+
+    #[test]
+    fn torus_ghosts_show_up_past_the_opposite_edge() {
+        let ghosts = |x: i64, y: i64| torus_ghosts(&cell(x, y), WIDTH, HEIGHT, ITEM_SIZE);
+
+        assert!(ghosts(10, 10).is_empty());
+        assert_eq!(ghosts(0, 5), vec![Position { x: 300.0, y: 75.0 }]);
+        assert_eq!(ghosts(19, 5), vec![Position { x: -15.0, y: 75.0 }]);
+        assert_eq!(ghosts(7, 19), vec![Position { x: 105.0, y: -15.0 }]);
+
+        // * A corner cell shows up past both edges and diagonally across
+        let corner = ghosts(0, 0);
+        assert_eq!(corner.len(), 3);
+        assert!(corner.contains(&Position { x: 300.0, y: 300.0 }));
+    }
+
+    #[test]
+    fn splash_gives_way_after_its_duration() {
+        let mut app = new_app(Settings {
+            splash_seconds: 1.0,
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (0, 0));
+
+        app.update(0.5).unwrap();
+        assert_eq!(app.splash_left, 0.5);
+        assert_eq!(app.ticks, 0);
+        assert_eq!(app.clock.elapsed, 0.0);
+        assert!(app.drawn_layers().contains(&Layer::Splash));
+
+        app.update(0.5).unwrap();
+        assert_eq!(app.splash_left, 0.0);
+        assert!(!app.drawn_layers().contains(&Layer::Splash));
+
+        app.update(0.1).unwrap();
+        assert_eq!(app.ticks, 1);
+    }
+
+    #[test]
+    fn any_key_skips_the_splash() {
+        let mut app = new_app(Settings {
+            splash_seconds: 3.0,
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5), (4, 5)], Direction::Right, (0, 0));
+
+        press(&mut app, Key::Up);
+
+        assert_eq!(app.splash_left, 0.0);
+        assert!(app.turns.is_empty());
+    }
+
+    #[test]
+    fn eyes_face_the_way_the_head_is_going() {
+        // * A 15 pixel cell has 3 pixel eyes, each 3 or 9 pixels into the cell along each axis
+        let head = cell(1, 2);
+        let eyes = |direction| eye_squares(&head, direction, ITEM_SIZE);
+
+        assert_eq!(
+            eyes(Direction::Right),
+            [[24.0, 33.0, 3.0, 3.0], [24.0, 39.0, 3.0, 3.0]]
+        );
+        assert_eq!(
+            eyes(Direction::Left),
+            [[18.0, 33.0, 3.0, 3.0], [18.0, 39.0, 3.0, 3.0]]
+        );
+        assert_eq!(
+            eyes(Direction::Up),
+            [[18.0, 33.0, 3.0, 3.0], [24.0, 33.0, 3.0, 3.0]]
+        );
+        assert_eq!(
+            eyes(Direction::Down),
+            [[18.0, 39.0, 3.0, 3.0], [24.0, 39.0, 3.0, 3.0]]
+        );
+    }
+
+    #[test]
+    fn safe_path_starts_next_to_the_head_and_ends_on_the_food() {
+        let mut app = new_app(Settings::default());
+        place(&mut app, &[(5, 5), (4, 5)], Direction::Right, (8, 5));
+
+        assert_eq!(app.safe_path(), vec![cell(6, 5), cell(7, 5), cell(8, 5)]);
+
+        // * Across the wrap is shorter than the long way round
+        place(&mut app, &[(18, 3)], Direction::Right, (1, 3));
+        assert_eq!(app.safe_path(), vec![cell(19, 3), cell(0, 3), cell(1, 3)]);
+    }
+
+    #[test]
+    fn safe_path_is_empty_when_the_food_is_walled_off() {
+        let mut app = new_app(Settings {
+            patrols: pocket_walls(),
+            ..Settings::default()
+        });
+        place(&mut app, &[(10, 10)], Direction::Right, (5, 5));
+
+        assert!(app.safe_path().is_empty());
+    }
+
+    #[test]
+    fn pause_dim_layer_draws_only_while_paused() {
+        let mut app = new_app(Settings {
+            pause_dim: true,
+            ..Settings::default()
+        });
+        assert!(!app.drawn_layers().contains(&Layer::PauseDim));
+
+        press(&mut app, Key::Space);
+        assert!(app.drawn_layers().contains(&Layer::PauseDim));
+
+        press(&mut app, Key::Space);
+        assert!(!app.drawn_layers().contains(&Layer::PauseDim));
+    }
+
+    #[test]
+    fn burst_scales_its_particles_with_the_body() {
+        let short = Burst::new(
+            &snake(&[(5, 5), (4, 5), (3, 5)], Direction::Right),
+            0.0,
+            1.0,
+        );
+        assert_eq!(short.particles.len(), 3 * BURST_PARTICLES_PER_SEGMENT);
+
+        let long = snake(&[(5, 5), (4, 5), (3, 5), (2, 5), (1, 5)], Direction::Right);
+        assert_eq!(
+            Burst::new(&long, 0.0, 1.0).particles.len(),
+            5 * BURST_PARTICLES_PER_SEGMENT
+        );
+    }
+
+    #[test]
+    fn reachable_food_never_lands_in_a_walled_off_pocket() {
+        let mut app = new_app(Settings {
+            reachable_food: true,
+            patrols: pocket_walls(),
+            ..Settings::default()
+        });
+        place(&mut app, &[(10, 12), (9, 12)], Direction::Right, (0, 0));
+        app.food.queue.clear();
+
+        for _ in 0..200 {
+            app.respawn_food();
+            for food in app.food.cells() {
+                assert!(!POCKET.iter().any(|&(x, y)| food == cell(x, y)));
+            }
+        }
+    }
+
+    #[test]
+    fn reachable_food_accepts_queued_food_as_it_is() {
+        let mut app = new_app(Settings {
+            reachable_food: true,
+            patrols: pocket_walls(),
+            ..Settings::default()
+        });
+        place(&mut app, &[(10, 12), (9, 12)], Direction::Right, (0, 0));
+        app.food.queue = LinkedList::from([cell(5, 5), cell(1, 1)]);
+        app.food.cycle_queue = false;
+
+        app.respawn_food();
+        assert_eq!(app.food.position, cell(5, 5));
+        assert_eq!(app.food.queue, LinkedList::from([cell(1, 1)]));
+    }
+
+    #[test]
+    fn cursor_maps_to_the_cell_and_contents_under_it() {
+        let mut app = new_app(Settings {
+            patrols: vec![((3, 2), (3, 2))],
+            ..Settings::default()
+        });
+        place(&mut app, &[(0, 2), (1, 2)], Direction::Left, (2, 2));
+        let window = window([330.0, 330.0], 1.0);
+        let board = board_viewport(window, 15.0);
+
+        let contents = |cursor: [f64; 2]| {
+            let point = board_point(cursor, window, board);
+            let (column, row) = app.hovered_cell(point)?;
+            Some(app.cell_contents(&cell(column, row)))
+        };
+
+        assert_eq!(board_point([22.0, 52.0], window, board), [7.0, 37.0]);
+        assert_eq!(app.hovered_cell([7.0, 37.0]), Some((0, 2)));
+        assert_eq!(contents([22.0, 52.0]), Some("head"));
+        assert_eq!(contents([37.0, 52.0]), Some("snake"));
+        assert_eq!(contents([52.0, 52.0]), Some("food"));
+        assert_eq!(contents([67.0, 52.0]), Some("patrol"));
+        assert_eq!(contents([82.0, 52.0]), Some("empty"));
+        // * The margin is off the board
+        assert_eq!(contents([5.0, 52.0]), None);
+    }
+
+    #[test]
+    fn history_keeps_only_the_newest_snapshots() {
+        let mut app = new_app(Settings {
+            debug: true,
+            history_length: Some(3),
+            ..Settings::default()
+        });
+        place(&mut app, &[(5, 5)], Direction::Right, (0, 0));
+
+        for _ in 0..5 {
+            tick(&mut app);
+        }
+
+        // * Each snapshot is taken before its tick moves the snake, newest at the front
+        let history = app.history.as_ref().expect("debug mode keeps a history");
+        let heads: Vec<_> = history
+            .iter()
+            .map(|snapshot| snapshot.body.front().copied())
+            .collect();
+        assert_eq!(
+            heads,
+            vec![Some(cell(9, 5)), Some(cell(8, 5)), Some(cell(7, 5))]
+        );
+    }
+
+    #[test]
+    fn max_ticks_stops_after_exactly_that_many_ticks() {
+        let mut app = new_app(Settings::default());
+        place(&mut app, &[(5, 5)], Direction::Right, (0, 0));
+
+        for _ in 0..4 {
+            tick(&mut app);
+        }
+        assert!(!app.out_of_ticks(5));
+
+        // * Paused time does not use up the budget
+        press(&mut app, Key::Space);
+        tick(&mut app);
+        tick(&mut app);
+        assert!(!app.out_of_ticks(5));
+        press(&mut app, Key::Space);
+
+        tick(&mut app);
+        assert!(app.out_of_ticks(5));
+    }
+
+    #[test]
+    fn death_replay_frames_are_not_counted_as_ticks() {
+        let mut app = new_app(Settings {
+            death_replay: true,
+            ..Settings::default()
+        });
+        place(&mut app, &COILED, Direction::Right, (0, 0));
+
+        tick(&mut app);
+        assert!(app.replay.is_some());
+        let ticks = app.ticks;
+        for _ in 0..5 {
+            app.update(REPLAY_FRAME_SECONDS).unwrap();
+        }
+        assert_eq!(app.ticks, ticks);
+        assert!(!app.out_of_ticks(ticks + 1));
+    }
+
+    #[test]
+    fn death_replay_plays_the_last_ticks_oldest_first_then_ends() {
+        let mut app = new_app(Settings {
+            death_replay: true,
+            patrols: vec![((12, 5), (12, 5))],
+            ..Settings::default()
+        });
+        place(&mut app, &[(0, 5)], Direction::Right, (0, 0));
+
+        // * The head reaches the patrol on tick 12, two more than the history holds
+        for _ in 0..12 {
+            tick(&mut app);
+        }
+        let replay = app.replay.as_ref().expect("the crash starts a replay");
+        assert_eq!(replay.frames.len(), HISTORY_LENGTH);
+        assert_eq!(replay.fatal, cell(12, 5));
+
+        let mut heads = Vec::new();
+        while !app.replay.as_ref().unwrap().frames.is_empty() {
+            app.update(REPLAY_FRAME_SECONDS).unwrap();
+            assert!(!app.ended);
+            heads.push(app.snake.body.front().copied());
+        }
+        // * Each snapshot is from before its tick's move, so the last one is a cell short of the patrol
+        let expected: Vec<_> = (2..12).map(|x| Some(cell(x, 5))).collect();
+        assert_eq!(heads, expected);
+
+        app.update(REPLAY_FRAME_SECONDS).unwrap();
+        assert!(app.ended);
+    }
+
+    #[test]
+    fn double_food_pair_skips_patrol_cells() {
+        let snake = snake(&[(5, 4)], Direction::Right);
+        let patrols = [cell(4, 5), cell(6, 5)];
+        let taken = |pos: &Position| snake.self_collision(pos) || patrols.contains(pos);
+
+        assert_eq!(
+            food(5, 5).free_neighbour(WIDTH, HEIGHT, taken),
+            Some(cell(5, 6))
+        );
+    }
+
+    #[test]
+    fn hunger_keeps_counting_while_food_is_delayed() {
+        let mut app = new_app(Settings {
+            hunger_ticks: Some(4),
+            food_delay: 2,
+            ..Settings::default()
+        });
+        place(
+            &mut app,
+            &[(5, 5), (4, 5), (3, 5)],
+            Direction::Right,
+            (6, 5),
+        );
+
+        // * Eats on tick 1, the food shows up again on tick 3
+        tick(&mut app);
+        tick(&mut app);
+        tick(&mut app);
+        assert!(!app.food.hidden);
+        assert_eq!(app.food.position, cell(0, 19));
+        assert_eq!(app.snake.body.len(), 4);
+
+        tick(&mut app);
+        tick(&mut app);
+        assert_eq!(app.snake.body.len(), 3);
     }
 }